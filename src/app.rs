@@ -1,15 +1,29 @@
-use crate::args::ARGS;
+use crate::args::{input_dir_path, ARGS};
+use crate::files::color_scheme::{load_color_schemes, ColorSchemes};
+use crate::files::config::{Action, ImageBackend, SplitLayout};
+use crate::files::include::ExpandedLine;
 use crate::files::data_dir::get_data_dir;
-use crate::files::pmu::{get_input_file_path, pmu_to_paragraph, DEFAULT_DIAGRAM};
+use crate::files::embedded::EmbeddedDocument;
+use crate::files::pmu::{get_input_file_paths, pmu_to_paragraph};
+use crate::files::project_config::ProjectConfig;
+use crate::files::scratchpad::Scratchpad;
+use crate::files::session::{FileSession, RenderStatus};
+use crate::files::swap::{DiffLine, SwapFile};
+use crate::logic::lint::LintDiagnostic;
+use crate::logic::zoom::DEFAULT_ZOOM;
 use crate::widgets::text_input::TextInput;
+use image::DynamicImage;
 use parking_lot::RwLock;
-use ratatui::prelude::Backend;
+use ratatui::prelude::{Backend, Rect};
 use ratatui::Terminal;
 use ratatui_image::picker::Picker;
 use ratatui_image::protocol::StatefulProtocol;
-use std::fs;
+use crokey::KeyCombination;
+use ratatui_image::picker::ProtocolType;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 use throbber_widgets_tui::ThrobberState;
 use tokio_util::sync::CancellationToken;
 
@@ -20,64 +34,285 @@ pub struct App<'a> {
     pub data_dir: PathBuf,
 
     pub dark_mode: bool,
-    pub left_area_percentage: u16,
+    pub color_schemes: ColorSchemes,
+    /// Size of the editor, in percent of the width of the main area, or of its height in the vertical layout
+    pub editor_percentage: u16,
+    /// Editor above the preview instead of left of it
+    pub vertical_layout: bool,
+    /// Pane taking the whole main area, the other one being hidden
+    pub maximized_pane: Option<Pane>,
+    /// Area of the editor and the preview when last drawn, to find the divider under the mouse
+    pub split_area: Rect,
+    /// The divider follows the mouse until the button is released
+    pub dragging_split: bool,
+    /// Zoom of the preview, in percent of the image fitted to its pane
+    pub zoom: u16,
 
+    /// Buffer shown in the editor, held apart from the others so that it can be borrowed alongside the rest of the app
+    pub buffer: Buffer<'a>,
+    /// Other open buffers, in tab order, the current buffer being at `buffer_index`
+    pub other_buffers: Vec<Buffer<'a>>,
+    pub buffer_index: usize,
+
+    // Output
+    pub render_throbber_state: ThrobberState,
+    pub picker: Option<Picker>,
+
+    pub key_combinations: HashMap<KeyCombination, Action>,
+    /// Modal shown over the editor, receiving the keys
+    pub dialog: Option<Dialog>,
+    /// File list shown left of the editor
+    pub sidebar: Option<Sidebar>,
+    /// Preprocessed source shown below the preview
+    pub expanded_source: Option<ExpandedSourceView>,
+}
+
+/// File open in a tab, with its own text and preview
+pub struct Buffer<'a> {
     // Input
     pub input_file_path: PathBuf,
     pub text_input: TextInput<'a>,
+    /// Content of the file when it was last loaded or saved
+    pub saved_text: String,
+    /// Why the last save failed, cleared by the next successful one
+    pub save_error: Option<String>,
+    /// Set when the unsaved text was written to the swap file, which is then owned by this process
+    pub swap_written_at: Option<Instant>,
+    /// The text changed since it was last written to the swap file
+    pub swap_outdated: bool,
+    pub project_config: ProjectConfig,
+    pub lint_diagnostics: Vec<LintDiagnostic>,
+    pub file_session: FileSession,
+    /// File whose `!include` was followed to open this one, to go back to
+    pub included_from: Option<PathBuf>,
+    /// Files included by the diagram with their modification time when last rendered, rendering it again when they change
+    pub included_files: HashMap<PathBuf, Option<SystemTime>>,
+    /// Markdown or AsciiDoc document holding the diagram shown in the editor
+    pub embedded: Option<EmbeddedDocument>,
 
     // Output
     pub render_output: Arc<RwLock<RenderOutput>>,
     pub cancellation_token: CancellationToken,
-    pub render_throbber_state: ThrobberState,
-    pub picker: Option<Picker>
+    /// Set when the text changed, the render waiting for the debounce delay
+    pub render_requested_at: Option<Instant>,
+    pub expanded_source: Arc<RwLock<ExpandedSource>>,
+}
+
+impl Buffer<'_> {
+    pub fn new(input_file_path: PathBuf) -> Self {
+        Buffer {
+            input_file_path,
+            text_input: TextInput {
+                text: String::new(),
+                cursor_position: (0, 0),
+                scroll_offset: (0, 0),
+                render_fn: Box::new(pmu_to_paragraph),
+            },
+            saved_text: String::new(),
+            save_error: None,
+            swap_written_at: None,
+            swap_outdated: false,
+            project_config: ProjectConfig::default(),
+            lint_diagnostics: vec![],
+            file_session: FileSession::default(),
+            included_from: None,
+            included_files: HashMap::new(),
+            embedded: None,
+            render_output: Arc::new(RwLock::new(RenderOutput {
+                pending: false,
+                file_path: None,
+                image: None,
+                source_image: None,
+                zoom: DEFAULT_ZOOM,
+                render_error: None,
+                dark_mode: false,
+                time: String::new(),
+            })),
+            cancellation_token: CancellationToken::new(),
+            render_requested_at: None,
+            expanded_source: Arc::new(RwLock::new(ExpandedSource {
+                lines: vec![],
+                preprocessed: false,
+            })),
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.text_input.text != self.saved_text
+    }
+
+    /// Outcome of the last render, none while rendering or before the first one
+    pub fn render_status(&self) -> Option<RenderStatus> {
+        let render_output = self.render_output.read();
+
+        match (render_output.pending, &render_output.file_path, &render_output.render_error) {
+            (true, _, _) => None,
+            (false, Some(_), _) => Some(RenderStatus::Rendered),
+            (false, None, Some(_)) => Some(RenderStatus::Failed),
+            (false, None, None) => None
+        }
+    }
+
+    /// Name of the file, followed by the position of the diagram in a document
+    pub fn file_name(&self) -> String {
+        let file_name = self.input_file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        match &self.embedded {
+            Some(document) => format!("{} [{}/{}]", file_name, document.block_index + 1, document.blocks.len()),
+            None => file_name
+        }
+    }
+}
+
+pub enum Dialog {
+    Scratchpads {
+        scratchpads: Vec<Scratchpad>,
+        selected: usize,
+    },
+    SaveAs {
+        path: String,
+        error: Option<String>,
+    },
+    /// Offers to recover the text left in the swap file by a crashed process
+    Recover {
+        swap_file: SwapFile,
+        /// Changes from the saved text, when shown
+        diff: Option<Vec<DiffLine>>,
+        scroll: u16,
+    },
+    /// Opens a file in a new buffer
+    OpenFile {
+        path: String,
+        error: Option<String>,
+    },
+    /// Diagrams of the current document, to pick the one to edit
+    EmbeddedDiagrams {
+        selected: usize,
+    },
+    /// Asks what to do with the unsaved text before going on
+    UnsavedChanges {
+        pending_action: PendingAction,
+    },
+}
+
+/// Diagram files of a directory, filtered by typing when focused
+pub struct Sidebar {
+    pub dir_path: PathBuf,
+    pub file_paths: Vec<PathBuf>,
+    /// Outcome of the last render of the files, from their session
+    pub render_statuses: HashMap<PathBuf, RenderStatus>,
+    /// Fuzzy filter typed in the sidebar
+    pub filter: String,
+    /// Index in the filtered files
+    pub selected: usize,
+    /// The sidebar receives the keys instead of the editor
+    pub focused: bool,
+}
+
+/// Source of the diagram after preprocessing, each line pointing back to its origin
+pub struct ExpandedSource {
+    pub lines: Vec<ExpandedLine>,
+    /// Expanded by PlantUML's preprocessor, the includes only being inlined otherwise
+    pub preprocessed: bool,
+}
+
+pub struct ExpandedSourceView {
+    /// Index in the expanded lines
+    pub selected: usize,
+    /// The expanded source receives the keys instead of the editor
+    pub focused: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Pane {
+    Editor,
+    Preview,
+}
+
+/// Action waiting for the unsaved text to be saved or discarded
+pub enum PendingAction {
+    Quit,
+    /// Closes the current buffer
+    Close,
+    /// Edits another diagram of the current document
+    SwitchEmbeddedDiagram(usize),
 }
 
 pub struct RenderOutput {
     pub pending: bool,
     pub file_path: Option<PathBuf>,
     pub image: Option<StatefulProtocol>,
+    /// Rendered image, which the preview image is made from at the zoom of the app
+    pub source_image: Option<DynamicImage>,
+    /// Zoom of the preview image
+    pub zoom: u16,
     pub render_error: Option<String>,
+    /// Mode of the last render, the preview being outdated once toggled
+    pub dark_mode: bool,
     pub time: String,
 }
 
 impl<'a> App<'a> {
     pub fn new() -> anyhow::Result<App<'a>> {
         let data_dir = get_data_dir();
-        let input_file_path = get_input_file_path(&data_dir)?;
+        let input_file_paths = get_input_file_paths(&data_dir)?;
 
-        let text_input = match fs::exists(&input_file_path)? {
-            true => fs::read_to_string(&input_file_path)?,
-            false => DEFAULT_DIAGRAM.to_string()
-        };
-
-        Ok(App {
+        let mut app = App {
             should_quit: false,
             data_dir,
-            dark_mode: !ARGS.light_mode || ARGS.dark_mode,
-            left_area_percentage: 50,
-            input_file_path,
-            text_input: TextInput {
-                text: text_input,
-                cursor_position: (0, 0),
-                render_fn: Box::new(pmu_to_paragraph),
+            dark_mode: true,
+            // An invalid color scheme must not prevent the subcommands from running
+            color_schemes: match ARGS.command {
+                None => load_color_schemes()?,
+                Some(_) => ColorSchemes::default()
             },
-            render_output: Arc::new(RwLock::new(RenderOutput {
-                pending: false,
-                file_path: None,
-                image: None,
-                render_error: None,
-                time: String::new(),
-            })),
-            cancellation_token: CancellationToken::new(),
+            editor_percentage: 50,
+            vertical_layout: false,
+            maximized_pane: None,
+            split_area: Rect::default(),
+            dragging_split: false,
+            zoom: DEFAULT_ZOOM,
+            buffer: Buffer::new(input_file_paths.first().cloned().unwrap_or_default()),
+            other_buffers: vec![],
+            buffer_index: 0,
             render_throbber_state: ThrobberState::default(),
-            picker: Picker::from_query_stdio().ok(),
-        })
+            picker: None,
+            key_combinations: HashMap::new(),
+            dialog: None,
+            sidebar: None,
+            expanded_source: None,
+        };
+
+        if let Some((first_input_file_path, other_input_file_paths)) = input_file_paths.split_first() {
+            app.buffer = app.load_buffer(first_input_file_path.clone())?;
+
+            for input_file_path in other_input_file_paths {
+                let buffer = app.load_buffer(input_file_path.clone())?;
+                app.other_buffers.push(buffer);
+            }
+        }
+
+        app.apply_project_config()?;
+        app.restore_file_session();
+        app.vertical_layout = app.buffer.project_config.config.ui.layout == SplitLayout::Vertical;
+
+        if let Some(input_dir_path) = input_dir_path() {
+            app.open_sidebar(input_dir_path.clone());
+        }
+
+        // Querying the terminal would pollute the output of the subcommands
+        if ARGS.command.is_none() {
+            app.picker = create_picker(app.buffer.project_config.config.renderer.backend);
+        }
+
+        Ok(app)
     }
 
     pub async fn run<T: Backend>(&mut self, mut terminal: Terminal<T>) -> anyhow::Result<()> {
-        self.save_pmu_file()?;
+        self.autosave_pmu_file();
+        self.lint_text_input();
         self.render_plantuml().await?;
+        self.check_swap_file();
         terminal.draw(|frame| self.ui(frame))?;
 
         while !self.should_quit {
@@ -85,6 +320,25 @@ impl<'a> App<'a> {
             terminal.draw(|frame| self.ui(frame))?;
         }
 
-        Ok(())
+        self.close_swap_files();
+        self.save_sessions()
     }
-}
\ No newline at end of file
+}
+
+/// Image picker of the configured backend, none meaning that the diagrams are rendered as text
+fn create_picker(backend: ImageBackend) -> Option<Picker> {
+    let protocol_type = match backend {
+        ImageBackend::Auto => return Picker::from_query_stdio().ok(),
+        ImageBackend::Text => return None,
+        ImageBackend::Kitty => ProtocolType::Kitty,
+        ImageBackend::Sixel => ProtocolType::Sixel,
+        ImageBackend::Iterm2 => ProtocolType::Iterm2,
+        ImageBackend::Halfblocks => ProtocolType::Halfblocks,
+    };
+
+    // The query only gives the font size here, which is guessed when the terminal does not answer
+    let mut picker = Picker::from_query_stdio().unwrap_or_else(|_| Picker::from_fontsize((8, 16)));
+    picker.set_protocol_type(protocol_type);
+
+    Some(picker)
+}
@@ -1,22 +1,20 @@
 use std::path;
 use crate::files::pmu::PlantUmlExtensions;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use once_cell::sync::Lazy;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use crate::error::CliError;
 use crate::files::utils::expand_tilde;
+use crate::logic::batch::SkipStrategy;
+use crate::render::PlantUmlOptions;
 
-pub const ARGS: Lazy<Args> = Lazy::new(|| {
-    let mut args = Args::parse();
+/// Path standing for stdin when used as input, and stdout when used as output
+pub const STDIO_PATH: &str = "-";
 
-    if let Some(input_path) = args.input.as_mut() {
-        if !input_path.exists() {
-            panic!("Input \"{}\" does not exist.", input_path.display());
-        }
-
-        if !input_path.is_file() {
-            panic!("Input \"{}\" is not a file.", input_path.display());
-        }
+pub static ARGS: Lazy<Args> = Lazy::new(|| {
+    let mut args = Args::parse();
 
+    for input_path in args.inputs.iter_mut() {
         *input_path = expand_tilde(input_path);
         
         if let Ok(new_input_path) = path::absolute(&input_path) {
@@ -24,22 +22,32 @@ pub const ARGS: Lazy<Args> = Lazy::new(|| {
         }
     }
 
-    if let Some(command) = args.command.as_mut() {
-        match command {
-            Command::Render { output, .. } => {
-                if !output.exists() {
-                    panic!("Render \"{}\" does not exist.", output.display());
-                }
+    if let Some(plantuml_config) = args.plantuml_config.as_mut() {
+        *plantuml_config = expand_tilde(plantuml_config);
+    }
 
-                if !output.is_dir() {
-                    panic!("Render \"{}\" is not a directory.", output.display());
-                }
+    for include in args.includes.iter_mut() {
+        *include = expand_tilde(include);
+    }
 
+    if let Some(command) = args.command.as_mut() {
+        match command {
+            Command::Render { output: Some(output), .. } if output.as_os_str() != STDIO_PATH => {
                 *output = expand_tilde(output);
 
                 if let Ok(new_output_path) = path::absolute(&output) {
                     *output = new_output_path;
                 }
+            },
+            Command::Render { .. } | Command::Config { .. } => {},
+            Command::Fmt { files, .. } | Command::Lint { files, .. } | Command::Check { files, .. } => {
+                for file in files.iter_mut() {
+                    *file = expand_tilde(file);
+
+                    if let Ok(new_file_path) = path::absolute(&file) {
+                        *file = new_file_path;
+                    }
+                }
             }
         }
     }
@@ -47,13 +55,64 @@ pub const ARGS: Lazy<Args> = Lazy::new(|| {
     args
 });
 
+/// Checks the paths given on the command line, which can't be reported from the lazy parsing
+pub fn validate_args() -> Result<(), CliError> {
+    for input_path in &ARGS.inputs {
+        if !input_path.exists() {
+            return Err(CliError::Usage(format!("Input \"{}\" does not exist", input_path.display())));
+        }
+
+        // A directory is browsed in the sidebar of the TUI
+        if !input_path.is_file() && (ARGS.command.is_some() || !input_path.is_dir()) {
+            return Err(CliError::Usage(format!("Input \"{}\" is not a file", input_path.display())));
+        }
+    }
+
+    if ARGS.inputs.iter().filter(|input_path| input_path.is_dir()).count() > 1 {
+        return Err(CliError::Usage(String::from("Only one directory can be browsed")));
+    }
+
+    if let Some(scratch) = &ARGS.scratch
+        && (scratch.is_empty() || scratch.contains(['/', '\\']) || scratch.starts_with('.')) {
+        return Err(CliError::Usage(format!("Scratch name \"{}\" must be a plain file name", scratch)));
+    }
+
+    if let Some(Command::Render { output: Some(output), .. }) = &ARGS.command
+        && output.as_os_str() != STDIO_PATH && output.exists() && !output.is_dir() && output_file_extension(output).is_none() {
+        return Err(CliError::Usage(format!("Output \"{}\" is neither a directory nor a diagram file", output.display())));
+    }
+
+    Ok(())
+}
+
+/// Directory given among the inputs, listed in the sidebar
+pub fn input_dir_path() -> Option<&'static PathBuf> {
+    ARGS.inputs.iter().find(|input_path| input_path.is_dir())
+}
+
+/// Format of an output file path such as `out/diagram.svg`, none for directories
+pub fn output_file_extension(output: &Path) -> Option<PlantUmlExtensions> {
+    match output.is_dir() {
+        true => None,
+        false => output
+            .extension()
+            .and_then(|file_extension| PlantUmlExtensions::from_file_extension(&file_extension.to_string_lossy()))
+    }
+}
+
 #[derive(Parser)]
+// Input files are given before the subcommand, e.g. `plantui diagram.puml render`
+#[command(subcommand_precedence_over_arg = true)]
 pub struct Args {
     #[clap(subcommand)]
     pub command: Option<Command>,
 
-    /// PlantUML file to edit
-    pub input: Option<PathBuf>,
+    /// PlantUML files or Markdown and AsciiDoc documents to edit, each in its own tab, or a directory of diagrams to browse in the sidebar
+    pub inputs: Vec<PathBuf>,
+
+    /// Scratch diagram to open or create instead of the most recent one when no input file is given
+    #[arg(long, value_name = "NAME", conflicts_with = "inputs")]
+    pub scratch: Option<String>,
 
     /// Activate light mode instead of dark mode
     #[arg(global = true, short, long, default_value_t = false)]
@@ -62,17 +121,132 @@ pub struct Args {
     /// Activate dark mode instead of light mode
     #[arg(global = true, short, long, overrides_with = "light_mode", default_value_t = false)]
     pub dark_mode: bool,
+
+    /// Neither restore nor remember the cursor, scroll, split, mode, zoom and theme of the input files
+    #[arg(long, default_value_t = false)]
+    pub no_session: bool,
+
+    /// Define a PlantUML variable, overriding the project configuration
+    #[arg(global = true, short = 'D', long = "define", value_name = "KEY=VALUE", value_parser = parse_define)]
+    pub defines: Vec<(String, String)>,
+
+    /// PlantUML configuration file
+    #[arg(global = true, long, value_name = "FILE")]
+    pub plantuml_config: Option<PathBuf>,
+
+    /// File included in every diagram, as if "!include" was used
+    #[arg(global = true, short = 'I', long = "include", value_name = "FILE")]
+    pub includes: Vec<PathBuf>,
+
+    /// PlantUML theme applied to every diagram
+    #[arg(global = true, long)]
+    pub theme: Option<String>,
+
+    /// Charset of the PlantUML files
+    #[arg(global = true, long)]
+    pub charset: Option<String>,
+
+    /// Extra argument passed to PlantUML as is, can be repeated
+    #[arg(global = true, long = "plantuml-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub plantuml_args: Vec<String>,
+}
+
+impl Args {
+    /// PlantUML options given on the command line, overriding the project configuration
+    pub fn plantuml_options(&self) -> PlantUmlOptions {
+        PlantUmlOptions {
+            defines: self.defines.iter().cloned().collect(),
+            config: self.plantuml_config.clone(),
+            includes: self.includes.clone(),
+            theme: self.theme.clone(),
+            charset: self.charset.clone(),
+            args: self.plantuml_args.clone(),
+        }
+    }
+}
+
+fn parse_define(define: &str) -> Result<(String, String), String> {
+    let (key, value) = define.split_once('=').unwrap_or((define, ""));
+
+    match key.is_empty() {
+        true => Err(String::from("the variable name is empty")),
+        false => Ok((key.to_string(), value.to_string()))
+    }
 }
 
 #[derive(clap::Subcommand)]
 pub enum Command {
     /// Render the input to the output
     Render {
-        /// Output file directory path
-        output: PathBuf,
+        /// PlantUML files, Markdown and AsciiDoc documents or glob patterns to render, defaults to the input files. Use "-" to read the diagram from stdin
+        inputs: Vec<String>,
+
+        /// Output directory path, mirroring the input directory structure, or output file path such as "out/diagram.svg". Defaults to each input file directory. Use "-" to write on stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Output file names, with the {stem}, {name}, {index} and {ext} placeholders, e.g. "{stem}-{name}.{ext}"
+        #[arg(long)]
+        name_template: Option<String>,
+
+        /// Write the rendered output on stdout
+        #[arg(long, default_value_t = false)]
+        stdout: bool,
+
+        /// Extensions wanted for the output, separated by commas. Defaults to the output file extension, or png
+        #[arg(short, long, value_enum, value_delimiter = ',')]
+        extension: Vec<PlantUmlExtensions>,
+
+        /// Maximum number of diagrams rendered in parallel, defaults to the number of CPUs
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// How to detect the outputs that are already up to date and can be skipped
+        #[arg(long, value_enum, default_value_t = SkipStrategy::Mtime)]
+        skip: SkipStrategy,
+
+        /// How the results are reported
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+        format: OutputFormat,
+    },
+    /// Format the input files in place
+    Fmt {
+        /// PlantUML files to format, defaults to the input files
+        files: Vec<PathBuf>,
+
+        /// Only check that the files are formatted, exiting with an error otherwise
+        #[arg(long, default_value_t = false)]
+        check: bool,
+    },
+    /// Check the input files against PlantUML-specific lint rules
+    Lint {
+        /// PlantUML files to lint, defaults to the input files
+        files: Vec<PathBuf>,
+
+        /// How the diagnostics are reported
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+        format: OutputFormat,
+    },
+    /// Validate the syntax of the input files without rendering them
+    Check {
+        /// PlantUML files to check, defaults to the input files
+        files: Vec<PathBuf>,
 
-        /// Extension wanted for the output
-        #[arg(short, long, value_enum, default_value_t = PlantUmlExtensions::Png)]
-        extension: PlantUmlExtensions,
+        /// How the results are reported
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+        format: OutputFormat,
+    },
+    /// Print the effective configuration and where each setting comes from
+    Config {
+        /// How the configuration is reported
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Human)]
+        format: OutputFormat,
     },
+}
+
+#[derive(ValueEnum, Default, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
 }
\ No newline at end of file
@@ -1,21 +1,518 @@
 use crate::app::App;
-use crate::args::{Command, ARGS};
-use crate::render::{dark_mode_to_plantuml_mode, render_command};
+use crate::error::CliError;
+use crate::args::{output_file_extension, Command, OutputFormat, ARGS, STDIO_PATH};
+use crate::files::atomic_write::write_atomically;
+use crate::files::config::config_entries;
+use crate::files::pmu::PlantUmlExtensions;
+use crate::files::project_config::{load_project_config, PROJECT_CONFIG_FILE_NAME};
+use crate::logic::check::{check_plantuml, CheckResult};
+use crate::logic::format::format_plantuml;
+use crate::logic::lint::{lint_plantuml, LintDiagnostic, Severity};
+use crate::logic::batch::{collect_render_jobs, diagram_blocks, render_diagram_blocks, render_jobs, RenderFailure, RenderJobStatus, DEFAULT_NAME_TEMPLATE};
+use crate::files::utils::expand_tilde;
+use crate::render::{effective_plantuml_mode, effective_plantuml_options, parse_plantuml_error, render_pipe_command};
+use anyhow::{bail, Context};
+use serde::Serialize;
+use std::{fs, io};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::{env, path};
+use std::thread;
+use std::time::Instant;
+
+/// Name of the file rendered from stdin into an output directory
+const STDIN_OUTPUT_FILE_STEM: &str = "diagram";
 
 impl App<'_> {
     pub async fn handle_command(&mut self, command: &Command) -> anyhow::Result<()> {
         match command {
-            Command::Render { output, extension } => {
-                render_command(
-                    &extension.to_output_format(),
-                    dark_mode_to_plantuml_mode(!ARGS.light_mode && ARGS.dark_mode),
-                    output,
-                    &self.input_file_path
+            Command::Render { inputs, output, name_template, stdout, extension, jobs, skip, format } => {
+                let (inputs, output) = render_inputs_and_output(inputs, &ARGS.inputs, output.as_deref())?;
+
+                let to_stdout = *stdout || output.as_ref().is_some_and(|output| output.as_os_str() == STDIO_PATH);
+                let from_stdin = inputs.iter().any(|input| input == STDIO_PATH);
+
+                let output_file_path = output.as_ref().filter(|output| !to_stdout && output_file_extension(output).is_some());
+
+                let extensions = match (extension.is_empty(), output_file_path.and_then(|output_file_path| output_file_extension(output_file_path))) {
+                    (false, _) => PlantUmlExtensions::expand(extension),
+                    (true, Some(output_extension)) => vec![output_extension],
+                    (true, None) => vec![PlantUmlExtensions::Png],
+                };
+
+                if output_file_path.is_some() && extensions.len() > 1 {
+                    bail!(CliError::Usage(String::from("Only one extension can be rendered to an output file")));
+                }
+
+                // An output file is an output directory with a single name, indexed for files holding several diagrams
+                let (output_dir_path, name_template) = match output_file_path {
+                    Some(output_file_path) => (
+                        output_file_path.parent().map(Path::to_path_buf),
+                        Some(name_template.clone().unwrap_or_else(|| output_file_path.file_name().unwrap().to_string_lossy().to_string()))
+                    ),
+                    None => (output.clone(), name_template.clone())
+                };
+
+                if to_stdout || from_stdin {
+                    return render_pipe(&inputs, output_dir_path.as_deref(), name_template.as_deref(), &extensions, to_stdout).await;
+                }
+
+                let mut render_jobs_to_run = collect_render_jobs(&inputs, output_dir_path.as_deref(), name_template.as_deref())?;
+
+                if let Some(output_dir_path) = output_file_path.and(output_dir_path) {
+                    let [job] = render_jobs_to_run.as_mut_slice() else {
+                        bail!(CliError::Usage(String::from("Only one input can be rendered to an output file")));
+                    };
+
+                    job.output_dir_path = output_dir_path;
+                }
+                let max_jobs = jobs.unwrap_or_else(|| thread::available_parallelism().map(usize::from).unwrap_or(1));
+                let initial_time = Instant::now();
+
+                let results = render_jobs(
+                    render_jobs_to_run,
+                    &extensions,
+                    max_jobs,
+                    *skip
                 )
                     .await?;
+
+                let summaries = extensions
+                    .iter()
+                    .map(|extension| {
+                        let mut summary = RenderSummary {
+                            extension: extension.to_file_extension(),
+                            rendered: 0,
+                            up_to_date: 0,
+                            failed: 0,
+                        };
+
+                        for (_, _, status) in results.iter().filter(|(_, other, _)| other == extension) {
+                            match status {
+                                RenderJobStatus::Rendered => summary.rendered += 1,
+                                RenderJobStatus::UpToDate => summary.up_to_date += 1,
+                                RenderJobStatus::Failed(_) => summary.failed += 1
+                            }
+                        }
+
+                        summary
+                    })
+                    .collect::<Vec<_>>();
+
+                let duration_ms = initial_time.elapsed().as_millis();
+
+                match format {
+                    OutputFormat::Human => {
+                        for (job, extension, status) in &results {
+                            let file = job.input_file_path.display();
+                            let extension = extension.to_file_extension();
+
+                            match status {
+                                RenderJobStatus::Rendered => println!("Rendered \"{file}\" [{extension}]"),
+                                RenderJobStatus::UpToDate => {},
+                                RenderJobStatus::Failed(RenderFailure::Syntax { line: Some(line), message }) => eprintln!("{file}:{line}: error[{extension}]: {message}"),
+                                RenderJobStatus::Failed(RenderFailure::Syntax { line: None, message } | RenderFailure::Io { message } | RenderFailure::MissingRenderer { message }) => eprintln!("{file}: error[{extension}]: {message}"),
+                            }
+                        }
+
+                        for summary in &summaries {
+                            println!("{}: {} rendered, {} up to date, {} failed", summary.extension, summary.rendered, summary.up_to_date, summary.failed);
+                        }
+
+                        println!("Done in {} ms", duration_ms);
+                    },
+                    OutputFormat::Json => {
+                        let outputs = results
+                            .iter()
+                            .map(|(job, extension, status)| RenderReportOutput {
+                                file: job.input_file_path.clone(),
+                                extension: extension.to_file_extension(),
+                                status: match status {
+                                    RenderJobStatus::Rendered => "rendered",
+                                    RenderJobStatus::UpToDate => "up-to-date",
+                                    RenderJobStatus::Failed(_) => "failed"
+                                },
+                                error: match status {
+                                    RenderJobStatus::Failed(failure) => Some(failure.clone()),
+                                    _ => None
+                                },
+                            })
+                            .collect();
+
+                        let report = RenderReport {
+                            outputs,
+                            summaries,
+                            duration_ms,
+                        };
+
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    }
+                }
+
+                // The most actionable failure decides the exit code
+                let failures = results
+                    .iter()
+                    .filter_map(|(_, _, status)| match status {
+                        RenderJobStatus::Failed(failure) => Some(failure),
+                        _ => None
+                    })
+                    .collect::<Vec<_>>();
+
+                let failed_count = failures.len();
+
+                if let Some(failure) = failures.iter().find(|failure| matches!(failure, RenderFailure::MissingRenderer { .. })) {
+                    bail!(CliError::MissingRenderer(failure.message().to_string()));
+                }
+
+                if failures.iter().any(|failure| matches!(failure, RenderFailure::Syntax { .. })) {
+                    bail!(CliError::Syntax(format!("{} output(s) failed to render", failed_count)));
+                }
+
+                if failed_count > 0 {
+                    bail!(CliError::Io(format!("{} output(s) failed to render", failed_count)));
+                }
+            },
+            Command::Fmt { files, check } => {
+                let files = command_files(files)?;
+                let mut unformatted_files = vec![];
+
+                for file in files {
+                    let content = fs::read_to_string(&file)
+                        .with_context(|| format!("Could not read \"{}\"", file.display()))?;
+                    let formatted = format_plantuml(&content, load_project_config(&file)?.config.editor.tab_width);
+
+                    if formatted == content {
+                        continue;
+                    }
+
+                    if *check {
+                        println!("Would reformat \"{}\"", file.display());
+                    }
+                    else {
+                        write_atomically(&file, formatted.as_bytes())?;
+                        println!("Formatted \"{}\"", file.display());
+                    }
+
+                    unformatted_files.push(file);
+                }
+
+                if *check && !unformatted_files.is_empty() {
+                    bail!(CliError::Unformatted(format!("{} file(s) are not formatted", unformatted_files.len())));
+                }
+            },
+            Command::Lint { files, format } => {
+                let files = command_files(files)?;
+                let mut file_diagnostics = vec![];
+
+                for file in &files {
+                    let content = fs::read_to_string(file)
+                        .with_context(|| format!("Could not read \"{}\"", file.display()))?;
+                    let project_config = load_project_config(file)?;
+
+                    for diagnostic in lint_plantuml(&content, file, &project_config.lint) {
+                        file_diagnostics.push(FileLintDiagnostic {
+                            file: file.clone(),
+                            diagnostic,
+                        });
+                    }
+                }
+
+                let error_count = file_diagnostics.iter().filter(|file_diagnostic| file_diagnostic.diagnostic.severity == Severity::Error).count();
+                let warning_count = file_diagnostics.len() - error_count;
+
+                match format {
+                    OutputFormat::Human => {
+                        for FileLintDiagnostic { file, diagnostic } in &file_diagnostics {
+                            println!("{}:{}: {}[{}]: {}", file.display(), diagnostic.line, diagnostic.severity, diagnostic.rule, diagnostic.message);
+                        }
+
+                        println!("{} error(s), {} warning(s)", error_count, warning_count);
+                    },
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&file_diagnostics)?),
+                }
+
+                if error_count > 0 {
+                    bail!(CliError::Lint(format!("Linting failed with {} error(s)", error_count)));
+                }
+            },
+            Command::Config { format } => {
+                // The project configuration is looked for from the input file, or else from the working directory
+                let project_config = match ARGS.inputs.first() {
+                    Some(input) => load_project_config(input)?,
+                    None => load_project_config(&env::current_dir()?.join(PROJECT_CONFIG_FILE_NAME))?
+                };
+
+                let (config_path, entries) = config_entries(&project_config)?;
+
+                match format {
+                    OutputFormat::Human => {
+                        match config_path.is_file() {
+                            true => println!("# Configuration file: {}", config_path.display()),
+                            false => println!("# Configuration file: {} (not found)", config_path.display())
+                        }
+
+                        if let Some(project_config_path) = &project_config.path {
+                            println!("# Project configuration file: {}", project_config_path.display());
+                        }
+
+                        let key_width = entries.iter().map(|entry| entry.key.len() + entry.value.len() + 3).max().unwrap_or(0);
+
+                        for entry in &entries {
+                            let setting = format!("{} = {}", entry.key, entry.value);
+                            println!("{:key_width$}  # {}", setting, entry.source);
+                        }
+                    },
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&entries)?),
+                }
+            },
+            Command::Check { files, format } => {
+                let files = command_files(files)?;
+                let mut file_checks = vec![];
+
+                for file in &files {
+                    let content = fs::read_to_string(file)
+                        .with_context(|| format!("Could not read \"{}\"", file.display()))?;
+                    let working_dir_path = file.parent().unwrap();
+                    let plantuml_options = effective_plantuml_options(&load_project_config(file)?);
+
+                    let result = match check_plantuml(&content, working_dir_path, &plantuml_options).await {
+                        Ok(result) => result,
+                        Err(err) => match err.downcast_ref::<io::Error>() {
+                            Some(io_error) if io_error.kind() == io::ErrorKind::NotFound => bail!(CliError::missing_renderer()),
+                            _ => return Err(err)
+                        }
+                    };
+
+                    file_checks.push(FileCheck {
+                        file: file.clone(),
+                        result,
+                    });
+                }
+
+                let failed_count = file_checks.iter().filter(|file_check| !file_check.result.errors.is_empty()).count();
+
+                match format {
+                    OutputFormat::Human => {
+                        for FileCheck { file, result } in &file_checks {
+                            for diagram in &result.diagrams {
+                                println!("{}:{}: {}", file.display(), diagram.line, diagram.diagram_type);
+                            }
+
+                            for error in &result.errors {
+                                match error.line {
+                                    Some(line) => eprintln!("{}:{}: error: {}", file.display(), line, error.message),
+                                    None => eprintln!("{}: error: {}", file.display(), error.message)
+                                }
+                            }
+                        }
+
+                        println!("{} file(s) checked, {} failed", file_checks.len(), failed_count);
+                    },
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&file_checks)?),
+                }
+
+                if failed_count > 0 {
+                    bail!(CliError::Syntax(format!("{} file(s) failed the syntax check", failed_count)));
+                }
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Inputs and output of a render, the input files given before `render` being rendered when it has no inputs.
+/// The former `plantui <input> render <output dir>` form is still understood when the output directory exists.
+fn render_inputs_and_output(render_inputs: &[String], input_file_paths: &[PathBuf], output: Option<&Path>) -> anyhow::Result<(Vec<String>, Option<PathBuf>)> {
+    let top_level_inputs = || input_file_paths.iter().map(|input| input.to_string_lossy().to_string()).collect::<Vec<_>>();
+
+    match (render_inputs, input_file_paths.is_empty()) {
+        ([], true) => bail!(CliError::Usage(String::from("No input file given"))),
+        ([], false) => Ok((top_level_inputs(), output.map(Path::to_path_buf))),
+        (_, true) => Ok((render_inputs.to_vec(), output.map(Path::to_path_buf))),
+        ([output_dir], false) if output.is_none() && expand_tilde(Path::new(output_dir)).is_dir() => {
+            Ok((top_level_inputs(), Some(path::absolute(expand_tilde(Path::new(output_dir)))?)))
+        },
+        (_, false) => bail!(CliError::Usage(String::from("Inputs are given both before and after \"render\", the output directory is now given with -o/--output, e.g. \"plantui render diagram.puml -o out\""))),
+    }
+}
+
+/// Renders a single diagram through PlantUML's pipe mode, reading it from stdin and/or writing it to stdout
+async fn render_pipe(inputs: &[String], output_dir_path: Option<&Path>, name_template: Option<&str>, extensions: &[PlantUmlExtensions], to_stdout: bool) -> anyhow::Result<()> {
+    let [input] = inputs else {
+        bail!(CliError::Usage(String::from("Only one input can be rendered from stdin or to stdout")));
+    };
+
+    if to_stdout && extensions.len() > 1 {
+        bail!(CliError::Usage(String::from("Only one extension can be rendered to stdout")));
+    }
+
+    let (input_name, input_path, stem, content, working_dir_path) = match input.as_str() {
+        STDIO_PATH => {
+            let mut content = vec![];
+            io::stdin().read_to_end(&mut content)?;
+
+            let working_dir_path = env::current_dir()?;
+            (String::from("<stdin>"), working_dir_path.join(STDIN_OUTPUT_FILE_STEM), String::from(STDIN_OUTPUT_FILE_STEM), content, working_dir_path)
+        },
+        _ => {
+            let input_file_path = path::absolute(expand_tilde(Path::new(input)))?;
+
+            if !input_file_path.is_file() {
+                bail!(CliError::Usage(format!("Input \"{}\" does not exist or is not a file", input_file_path.display())));
+            }
+
+            let content = fs::read(&input_file_path)?;
+            let stem = input_file_path.file_stem().unwrap().to_string_lossy().to_string();
+            let working_dir_path = input_file_path.parent().unwrap().to_path_buf();
+            (input_file_path.display().to_string(), input_file_path, stem, content, working_dir_path)
+        }
+    };
+
+    // Stdin is considered as a file of the working directory to find the project configuration
+    let project_config = load_project_config(&working_dir_path.join(&stem))?;
+    let plantuml_options = effective_plantuml_options(&project_config);
+    let mode = effective_plantuml_mode(&project_config);
+
+    if !to_stdout {
+        let output_dir_path = output_dir_path
+            .or(project_config.render.output_dir.as_deref())
+            .unwrap_or(&working_dir_path);
+        let name_template = name_template
+            .or(project_config.render.name_template.as_deref())
+            .unwrap_or(DEFAULT_NAME_TEMPLATE);
+        let content = String::from_utf8_lossy(&content);
+
+        for extension in extensions {
+            let file_extension = extension.to_file_extension();
+
+            match render_diagram_blocks(&diagram_blocks(&input_path, &content), &stem, name_template, output_dir_path, &working_dir_path, *extension, mode, &plantuml_options).await {
+                Ok(output_file_paths) => {
+                    for output_file_path in output_file_paths {
+                        println!("Rendered \"{}\" [{}]", output_file_path.display(), file_extension);
+                    }
+                },
+                Err(RenderFailure::MissingRenderer { message }) => bail!(CliError::MissingRenderer(message)),
+                Err(RenderFailure::Io { message }) => bail!(CliError::Io(message)),
+                Err(RenderFailure::Syntax { line, message }) => {
+                    match line {
+                        Some(line) => eprintln!("{input_name}:{line}: error[{file_extension}]: {message}"),
+                        None => eprintln!("{input_name}: error[{file_extension}]: {message}")
+                    }
+
+                    bail!(CliError::Syntax(String::from("The diagram failed to render")));
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    for extension in extensions {
+        let output = match render_pipe_command(&extension.to_output_format(), mode, &plantuml_options, &content, &working_dir_path).await {
+            Ok(output) => output,
+            Err(err) => match err.downcast_ref::<io::Error>() {
+                Some(io_error) if io_error.kind() == io::ErrorKind::NotFound => bail!(CliError::missing_renderer()),
+                _ => return Err(err)
+            }
+        };
+
+        if !output.status.success() {
+            let (line, message) = parse_plantuml_error(&String::from_utf8_lossy(&output.stderr));
+
+            match line {
+                Some(line) => eprintln!("{input_name}:{line}: error[{}]: {message}", extension.to_file_extension()),
+                None => eprintln!("{input_name}: error[{}]: {message}", extension.to_file_extension())
+            }
+
+            bail!(CliError::Syntax(String::from("The diagram failed to render")));
+        }
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&output.stdout)?;
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct RenderReport {
+    outputs: Vec<RenderReportOutput>,
+    summaries: Vec<RenderSummary>,
+    duration_ms: u128,
+}
+
+#[derive(Serialize)]
+struct RenderReportOutput {
+    file: PathBuf,
+    extension: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RenderFailure>,
+}
+
+#[derive(Serialize)]
+struct RenderSummary {
+    extension: String,
+    rendered: usize,
+    up_to_date: usize,
+    failed: usize,
+}
+
+#[derive(Serialize)]
+struct FileCheck {
+    file: PathBuf,
+    #[serde(flatten)]
+    result: CheckResult,
+}
+
+#[derive(Serialize)]
+struct FileLintDiagnostic {
+    file: PathBuf,
+    #[serde(flatten)]
+    diagnostic: LintDiagnostic,
+}
+
+/// Files given to a subcommand, falling back to the input files
+fn command_files(files: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    match (files.is_empty(), ARGS.inputs.is_empty()) {
+        (false, _) => Ok(files.to_vec()),
+        (true, false) => Ok(ARGS.inputs.clone()),
+        (true, true) => bail!(CliError::Usage(String::from("No input file given"))),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::error_exit_code;
+    use std::slice;
+
+    #[test]
+    fn renders_the_inputs_given_before_render_into_a_legacy_output_dir() {
+        let input_file_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/bob_alice.pmu");
+        let output_dir = env!("CARGO_MANIFEST_DIR").to_string();
+
+        let (inputs, output) = render_inputs_and_output(slice::from_ref(&output_dir), slice::from_ref(&input_file_path), None).unwrap();
+
+        assert_eq!(inputs, [input_file_path.to_string_lossy().to_string()]);
+        assert_eq!(output, Some(PathBuf::from(output_dir)));
+    }
+
+    #[test]
+    fn refuses_inputs_both_before_and_after_render() {
+        let input_file_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/bob_alice.pmu");
+        let err = render_inputs_and_output(&[String::from("missing_output_dir")], &[input_file_path], None).unwrap_err();
+
+        assert!(err.to_string().contains("-o/--output"));
+        assert_eq!(error_exit_code(&err), 2);
+    }
+
+    #[test]
+    fn renders_the_render_inputs_with_the_output_option() {
+        let (inputs, output) = render_inputs_and_output(&[String::from("docs/*.puml")], &[], Some(Path::new("out"))).unwrap();
+
+        assert_eq!(inputs, ["docs/*.puml"]);
+        assert_eq!(output, Some(PathBuf::from("out")));
+    }
+}
@@ -0,0 +1,56 @@
+use crate::render::PLANTUML_COMMAND;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+/// Errors mapped to distinct exit codes, so that scripts can tell them apart
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    MissingRenderer(String),
+    Syntax(String),
+    Io(String),
+    /// Files that `fmt --check` would reformat
+    Unformatted(String),
+    /// Lint diagnostics of the error severity
+    Lint(String),
+}
+
+impl CliError {
+    pub fn missing_renderer() -> CliError {
+        CliError::MissingRenderer(format!("PlantUML command \"{}\" was not found, install it, set the PLANT_UML env var or renderer.command in the configuration", &*PLANTUML_COMMAND))
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Usage(_) => 2,
+            CliError::MissingRenderer(_) => 3,
+            CliError::Syntax(_) => 4,
+            CliError::Io(_) => 5,
+            CliError::Unformatted(_) => 6,
+            CliError::Lint(_) => 7,
+        }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) | CliError::MissingRenderer(message) | CliError::Syntax(message) | CliError::Io(message) | CliError::Unformatted(message) | CliError::Lint(message) => f.write_str(message)
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+pub fn error_exit_code(err: &anyhow::Error) -> u8 {
+    if let Some(cli_error) = err.downcast_ref::<CliError>() {
+        cli_error.exit_code()
+    }
+    else if err.downcast_ref::<io::Error>().is_some() {
+        CliError::Io(String::new()).exit_code()
+    }
+    else {
+        1
+    }
+}
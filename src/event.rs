@@ -1,69 +1,114 @@
-use crate::app::App;
+use crate::app::{App, Pane};
+use crate::files::config::Action;
 use crokey::crossterm::event;
 use crokey::crossterm::event::{Event, KeyCode, KeyEvent};
 use crokey::OneToThree::One;
 use crokey::{key, KeyCombination};
-use std::time::Duration;
-
-const TICK_RATE: Duration = Duration::from_millis(200);
+use std::time::{Duration, Instant};
 
 impl App<'_> {
     pub async fn handle_events(&mut self) -> anyhow::Result<()> {
-        if event::poll(TICK_RATE)? {
-            if let Ok(event) = event::read() {
-                if let Event::Key(key) = event {
+        let tick_rate = Duration::from_millis(self.buffer.project_config.config.ui.tick_rate_ms);
+        let debounce = Duration::from_millis(self.buffer.project_config.config.ui.debounce_ms);
+
+        let timeout = match self.buffer.render_requested_at {
+            Some(render_requested_at) => debounce.saturating_sub(render_requested_at.elapsed()).min(tick_rate),
+            None => tick_rate
+        };
+
+        if event::poll(timeout)? {
+            match event::read() {
+                Ok(Event::Key(key)) => {
+                    let previous_input_file_path = self.buffer.input_file_path.clone();
+                    let previous_text = self.buffer.text_input.text.clone();
                     let missed_input = self.handle_event(key)?;
 
                     if !missed_input && !self.should_quit {
-                        self.save_pmu_file()?;
-                        self.render_plantuml().await?;
+                        // Actions such as toggling the mode or the theme render again without editing the text
+                        if self.buffer.input_file_path == previous_input_file_path && self.buffer.text_input.text != previous_text {
+                            self.autosave_pmu_file();
+                            self.buffer.swap_outdated = true;
+                        }
+
+                        self.lint_text_input();
+                        self.buffer.render_requested_at = Some(Instant::now());
                     }
-                }
+                },
+                Ok(Event::Mouse(mouse_event)) => self.handle_mouse_event(mouse_event),
+                _ => {}
             }
         }
 
+        self.update_swap_files();
+        self.check_included_files();
+
+        if let Some(render_requested_at) = self.buffer.render_requested_at
+            && render_requested_at.elapsed() >= debounce {
+            self.buffer.render_requested_at = None;
+            self.render_plantuml().await?;
+        }
+
         Ok(())
     }
 
     fn handle_event(&mut self, key_event: KeyEvent) -> anyhow::Result<bool> {
         let key_combination = KeyCombination::from(key_event);
+
+        let action = self.key_combinations.get(&key_combination).copied();
+
+        // The dialog takes every key but the quit ones
+        if self.dialog.is_some() && action != Some(Action::Quit) {
+            let should_render = self.handle_dialog_event(key_combination)?;
+            return Ok(!should_render);
+        }
+
+        if let Some(action) = action {
+            let should_render = self.run_action(action)?;
+            return Ok(!should_render);
+        }
+
+        if self.expanded_source.as_ref().is_some_and(|expanded_source| expanded_source.focused) {
+            let should_render = self.handle_expanded_source_event(key_combination)?;
+            return Ok(!should_render);
+        }
+
+        if self.sidebar.as_ref().is_some_and(|sidebar| sidebar.focused) {
+            let should_render = self.handle_sidebar_event(key_combination)?;
+            return Ok(!should_render);
+        }
+
         let mut missed_input = false;
         let mut should_render = false;
 
         match key_combination {
-            key!(ctrl-c) => self.should_quit = true,
-            key!(ctrl-Y) | key!(ctrl-shift-Y) => self.copy_to_clipboard()?,
-
-            key!(alt-left) => self.shrink_left_area(),
-            key!(alt-right) => self.expand_left_area(),
-            key!(ctrl-D) | key!(ctrl-shift-D) => {
-                self.dark_mode = !self.dark_mode;
+            key!(tab) => {
+                self.buffer.text_input.enter_str(&" ".repeat(self.buffer.project_config.config.editor.tab_width));
                 should_render = true;
             },
 
             key!(delete) => {
-                self.text_input.delete_char_forward();
+                self.buffer.text_input.delete_char_forward();
                 should_render = true;
             },
             key!(backspace) => {
-                self.text_input.delete_char_backward();
+                self.buffer.text_input.delete_char_backward();
                 should_render = true;
             },
             key!(enter) => {
-                self.text_input.enter_char('\n');
-                self.text_input.move_cursor_down();
+                self.buffer.text_input.enter_char('\n');
+                self.buffer.text_input.move_cursor_down();
                 should_render = true;
             },
-            key!(home) => self.text_input.move_cursor_line_start(),
-            key!(end) => self.text_input.move_cursor_line_end(),
+            key!(home) => self.buffer.text_input.move_cursor_line_start(),
+            key!(end) => self.buffer.text_input.move_cursor_line_end(),
 
-            key!(up) => self.text_input.move_cursor_up(),
-            key!(down) => self.text_input.move_cursor_down(),
-            key!(left) => self.text_input.move_cursor_left(),
-            key!(right) => self.text_input.move_cursor_right(),
+            key!(up) => self.buffer.text_input.move_cursor_up(),
+            key!(down) => self.buffer.text_input.move_cursor_down(),
+            key!(left) => self.buffer.text_input.move_cursor_left(),
+            key!(right) => self.buffer.text_input.move_cursor_right(),
 
             KeyCombination { codes: One(KeyCode::Char(char)), .. } => {
-                self.text_input.enter_char(char);
+                self.buffer.text_input.enter_char(char);
                 should_render = true;
             },
 
@@ -72,4 +117,54 @@ impl App<'_> {
 
         Ok(missed_input || !should_render)
     }
-}
\ No newline at end of file
+
+    /// Runs a bound action, returning whether the diagram must be rendered again
+    fn run_action(&mut self, action: Action) -> anyhow::Result<bool> {
+        match action {
+            Action::Quit => self.quit(),
+            Action::Copy => self.copy_to_clipboard()?,
+            Action::ShrinkEditor => self.shrink_editor(),
+            Action::ExpandEditor => self.expand_editor(),
+            Action::ShrinkEditorFinely => self.shrink_editor_finely(),
+            Action::ExpandEditorFinely => self.expand_editor_finely(),
+            Action::ToggleLayout => self.toggle_layout(),
+            Action::MaximizeEditor => self.toggle_maximized_pane(Pane::Editor),
+            Action::MaximizePreview => self.toggle_maximized_pane(Pane::Preview),
+            Action::ToggleDarkMode => {
+                self.dark_mode = !self.dark_mode;
+                return Ok(true);
+            },
+            Action::NextTheme => {
+                self.next_theme()?;
+                return Ok(true);
+            },
+            Action::PreviousTheme => {
+                self.previous_theme()?;
+                return Ok(true);
+            },
+            Action::Format => {
+                self.format_text_input();
+                return Ok(true);
+            },
+            Action::Scratchpads => self.open_scratchpads_dialog(),
+            Action::NewScratchpad => return self.new_scratchpad(),
+            Action::Save => {
+                self.save_pmu_file();
+            },
+            Action::SaveAs => self.open_save_as_dialog(),
+            Action::OpenFile => self.open_open_file_dialog(),
+            Action::NextBuffer => return self.next_buffer(),
+            Action::PreviousBuffer => return self.previous_buffer(),
+            Action::CloseBuffer => return self.request_close_buffer(),
+            Action::ToggleSidebar => self.toggle_sidebar(),
+            Action::GoToInclude => return self.go_to_include(),
+            Action::GoBack => return self.go_back(),
+            Action::ToggleExpandedSource => self.toggle_expanded_source(),
+            Action::EmbeddedDiagrams => self.open_embedded_diagrams_dialog(),
+            Action::ZoomIn => self.zoom_in(),
+            Action::ZoomOut => self.zoom_out(),
+        }
+
+        Ok(false)
+    }
+}
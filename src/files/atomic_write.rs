@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Context};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Replaces the file content at once: the file is either left untouched or fully written, even if the process crashes.
+/// Symlinks are followed and the permissions and ownership of the existing file are kept.
+pub fn write_atomically(path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    let target_path = resolve_symlinks(path)?;
+    let dir_path = match target_path.parent() {
+        Some(dir_path) if !dir_path.as_os_str().is_empty() => dir_path.to_path_buf(),
+        _ => PathBuf::from(".")
+    };
+
+    // The temp file is in the same directory for the rename to be atomic
+    let temp_path = dir_path.join(format!(
+        ".{}.{}-{}.tmp",
+        target_path.file_name().unwrap_or_default().to_string_lossy(),
+        process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = write_temp_file(&temp_path, &target_path, content)
+        .and_then(|()| fs::rename(&temp_path, &target_path).map_err(|err| anyhow!(err)));
+
+    if let Err(err) = result {
+        fs::remove_file(&temp_path).ok();
+        return Err(err).with_context(|| format!("Could not save \"{}\"", path.display()));
+    }
+
+    sync_dir(&dir_path);
+
+    Ok(())
+}
+
+/// Path of the file that a symlink, possibly chained, points to
+fn resolve_symlinks(path: &Path) -> anyhow::Result<PathBuf> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => fs::canonicalize(path)
+            .with_context(|| format!("Could not resolve the symlink \"{}\"", path.display())),
+        _ => Ok(path.to_path_buf())
+    }
+}
+
+fn write_temp_file(temp_path: &Path, target_path: &Path, content: &[u8]) -> anyhow::Result<()> {
+    let mut temp_file = File::options()
+        .write(true)
+        .create_new(true)
+        .open(temp_path)?;
+
+    if let Ok(metadata) = fs::metadata(target_path) {
+        temp_file.set_permissions(metadata.permissions())?;
+        preserve_ownership(temp_path, &metadata);
+    }
+
+    temp_file.write_all(content)?;
+    temp_file.sync_all()?;
+
+    Ok(())
+}
+
+/// Only the owner or root can change the ownership, the file being saved anyway
+#[cfg(unix)]
+fn preserve_ownership(temp_path: &Path, metadata: &fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+
+    std::os::unix::fs::chown(temp_path, Some(metadata.uid()), Some(metadata.gid())).ok();
+}
+
+#[cfg(not(unix))]
+fn preserve_ownership(_temp_path: &Path, _metadata: &fs::Metadata) {}
+
+/// Persists the rename itself
+#[cfg(unix)]
+fn sync_dir(dir_path: &Path) {
+    if let Ok(dir) = File::open(dir_path) {
+        dir.sync_all().ok();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir_path: &Path) {}
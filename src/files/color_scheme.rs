@@ -0,0 +1,153 @@
+use crate::files::config_dir::get_config_dir;
+use anyhow::anyhow;
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+pub const COLOR_SCHEME_FILE_NAME: &str = "color_scheme.toml";
+
+/// Colors of the TUI itself, PlantUML's output being styled by its own themes
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ColorScheme {
+    // Editor tokens
+    pub start_end: Color,
+    pub comment: Color,
+    pub include: Color,
+    pub title: Color,
+    pub header_footer: Color,
+    pub define: Color,
+    pub legend: Color,
+    pub skinparam: Color,
+    pub empty: Color,
+    pub text: Color,
+
+    // UI chrome
+    pub app_name: Color,
+    pub file_name: Color,
+    pub title_brackets: Color,
+    pub border: Color,
+    pub status: Color,
+    pub highlight: Color,
+
+    // Diagnostics
+    pub error: Color,
+    pub warning: Color,
+
+    // Diffs
+    pub diff_added: Color,
+    pub diff_removed: Color,
+}
+
+impl ColorScheme {
+    pub fn dark() -> ColorScheme {
+        ColorScheme {
+            start_end: Color::Blue,
+            comment: Color::DarkGray,
+            include: Color::Yellow,
+            title: Color::Cyan,
+            header_footer: Color::Magenta,
+            define: Color::Yellow,
+            legend: Color::LightMagenta,
+            skinparam: Color::Green,
+            empty: Color::Gray,
+            text: Color::Reset,
+            app_name: Color::Gray,
+            file_name: Color::Reset,
+            title_brackets: Color::DarkGray,
+            border: Color::Reset,
+            status: Color::DarkGray,
+            highlight: Color::Cyan,
+            error: Color::Red,
+            warning: Color::Yellow,
+            diff_added: Color::Green,
+            diff_removed: Color::Red,
+        }
+    }
+
+    pub fn light() -> ColorScheme {
+        ColorScheme {
+            start_end: Color::Blue,
+            comment: Color::Gray,
+            include: Color::Rgb(175, 95, 0),
+            title: Color::Rgb(0, 95, 135),
+            header_footer: Color::Magenta,
+            define: Color::Rgb(175, 95, 0),
+            legend: Color::Rgb(135, 0, 135),
+            skinparam: Color::Rgb(0, 95, 0),
+            empty: Color::DarkGray,
+            text: Color::Reset,
+            app_name: Color::DarkGray,
+            file_name: Color::Reset,
+            title_brackets: Color::Gray,
+            border: Color::Gray,
+            status: Color::Gray,
+            highlight: Color::Rgb(0, 95, 135),
+            error: Color::Rgb(215, 0, 0),
+            warning: Color::Rgb(175, 95, 0),
+            diff_added: Color::Rgb(0, 95, 0),
+            diff_removed: Color::Rgb(215, 0, 0),
+        }
+    }
+}
+
+/// Palettes following the dark mode toggle
+#[derive(Clone, Copy)]
+pub struct ColorSchemes {
+    pub dark: ColorScheme,
+    pub light: ColorScheme,
+}
+
+impl Default for ColorSchemes {
+    fn default() -> Self {
+        ColorSchemes {
+            dark: ColorScheme::dark(),
+            light: ColorScheme::light(),
+        }
+    }
+}
+
+impl ColorSchemes {
+    pub fn get(&self, dark_mode: bool) -> &ColorScheme {
+        match dark_mode {
+            true => &self.dark,
+            false => &self.light
+        }
+    }
+}
+
+/// Built-in palettes overridden by the `[dark]` and `[light]` tables of the user color scheme file
+pub fn load_color_schemes() -> anyhow::Result<ColorSchemes> {
+    let color_scheme_path = get_config_dir().join(COLOR_SCHEME_FILE_NAME);
+    let mut color_schemes = ColorSchemes::default();
+
+    if !color_scheme_path.is_file() {
+        return Ok(color_schemes);
+    }
+
+    let content = fs::read_to_string(&color_scheme_path)?;
+    let invalid = |err: &dyn std::fmt::Display| anyhow!("Invalid color scheme \"{}\"\n{}", color_scheme_path.display(), err);
+
+    let mut overrides = content.parse::<toml::Table>().map_err(|err| invalid(&err))?;
+
+    for (name, color_scheme) in [("dark", &mut color_schemes.dark), ("light", &mut color_schemes.light)] {
+        let Some(palette_overrides) = overrides.remove(name) else {
+            continue;
+        };
+
+        let mut palette = toml::Table::try_from(*color_scheme)?;
+
+        match palette_overrides {
+            toml::Value::Table(palette_overrides) => palette.extend(palette_overrides),
+            _ => return Err(invalid(&format!("\"{name}\" must be a table")))
+        }
+
+        *color_scheme = palette.try_into().map_err(|err| invalid(&err))?;
+    }
+
+    if let Some(name) = overrides.keys().next() {
+        return Err(invalid(&format!("unknown palette \"{name}\", expected \"dark\" or \"light\"")));
+    }
+
+    Ok(color_schemes)
+}
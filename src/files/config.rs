@@ -0,0 +1,482 @@
+use crate::args::ARGS;
+use crate::files::config_dir::get_config_dir;
+use crate::files::project_config::ProjectConfig;
+use crate::render::PlantUmlOptions;
+use anyhow::anyhow;
+use crokey::KeyCombination;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use strum::{EnumIter, IntoEnumIterator};
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+static LOADED_CONFIG: Lazy<anyhow::Result<LoadedConfig>> = Lazy::new(load_config);
+
+/// Configuration from the user configuration file and the environment, defaults being used when it is invalid
+pub static CONFIG: Lazy<Config> = Lazy::new(|| {
+    match &*LOADED_CONFIG {
+        Ok(loaded_config) => loaded_config.config.clone(),
+        Err(_) => Config::default()
+    }
+});
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub renderer: RendererConfig,
+    pub ui: UiConfig,
+    pub editor: EditorConfig,
+    pub keybindings: BTreeMap<Action, KeyBinding>,
+    pub plantuml: PlantUmlOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RendererConfig {
+    /// PlantUML command, which may hold arguments quoted as in a shell, e.g. `java -jar "~/My Tools/plantuml.jar"`
+    pub command: String,
+    /// How the preview images are displayed in the terminal
+    pub backend: ImageBackend,
+}
+
+impl RendererConfig {
+    /// Program and arguments of the command, split as a shell would
+    pub fn command_args(&self) -> anyhow::Result<Vec<String>> {
+        shell_words::split(&self.command).map_err(|err| anyhow!("Invalid renderer command \"{}\": {}", self.command, err))
+    }
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        RendererConfig {
+            command: String::from("plantuml"),
+            backend: ImageBackend::Auto,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageBackend {
+    /// Queries the terminal for its graphics protocol
+    #[default]
+    Auto,
+    Kitty,
+    Sixel,
+    Iterm2,
+    Halfblocks,
+    /// Renders the diagrams as Unicode text
+    Text,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct UiConfig {
+    /// Mode used when neither `--light-mode` nor `--dark-mode` is given
+    pub dark_mode: bool,
+    /// Size of the editor, in percent of the terminal
+    pub split: u16,
+    /// Size added or removed when resizing the editor, in percent of the terminal
+    pub resize_step: u16,
+    /// Editor left of the preview, or above it for narrow terminals
+    pub layout: SplitLayout,
+    /// Capture the mouse to drag the divider between the editor and the preview, the terminal no longer selecting text with it
+    pub mouse: bool,
+    /// Interval between two redraws when idle
+    pub tick_rate_ms: u64,
+    /// Delay without typing before the diagram is rendered again
+    pub debounce_ms: u64,
+    /// Width of the file sidebar, in columns
+    pub sidebar_width: u16,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        UiConfig {
+            dark_mode: true,
+            split: 50,
+            resize_step: 10,
+            layout: SplitLayout::Horizontal,
+            mouse: false,
+            tick_rate_ms: 200,
+            debounce_ms: 0,
+            sidebar_width: 30,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SplitLayout {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EditorConfig {
+    /// Spaces inserted by the tab key and used by the formatter for each indentation level
+    pub tab_width: usize,
+    /// Lint the diagram while typing
+    pub live_lint: bool,
+    /// Save the file on every change instead of on demand
+    pub autosave: bool,
+    /// Minimum delay between two writes of the unsaved text to the swap file, 0 disabling it
+    pub swap_interval_ms: u64,
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        EditorConfig {
+            tab_width: 2,
+            live_lint: true,
+            autosave: false,
+            swap_interval_ms: 2000,
+        }
+    }
+}
+
+/// Actions which can be bound to keys, each one bound by default
+#[derive(Serialize, Deserialize, EnumIter, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    Save,
+    Copy,
+    ToggleDarkMode,
+    Format,
+    NextTheme,
+    PreviousTheme,
+    ShrinkEditor,
+    ExpandEditor,
+    ShrinkEditorFinely,
+    ExpandEditorFinely,
+    ToggleLayout,
+    MaximizeEditor,
+    MaximizePreview,
+    Scratchpads,
+    NewScratchpad,
+    SaveAs,
+    OpenFile,
+    NextBuffer,
+    PreviousBuffer,
+    CloseBuffer,
+    ToggleSidebar,
+    GoToInclude,
+    GoBack,
+    ToggleExpandedSource,
+    EmbeddedDiagrams,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl Action {
+    fn default_keys(self) -> &'static str {
+        match self {
+            Action::Quit => "ctrl-c",
+            Action::Save => "ctrl-s",
+            Action::Copy => "ctrl-shift-y",
+            Action::ToggleDarkMode => "ctrl-shift-d",
+            Action::Format => "ctrl-shift-f",
+            Action::NextTheme => "ctrl-shift-t",
+            Action::PreviousTheme => "alt-shift-t",
+            Action::ShrinkEditor => "alt-left",
+            Action::ExpandEditor => "alt-right",
+            Action::ShrinkEditorFinely => "alt-shift-left",
+            Action::ExpandEditorFinely => "alt-shift-right",
+            Action::ToggleLayout => "ctrl-shift-l",
+            Action::MaximizeEditor => "alt-e",
+            Action::MaximizePreview => "alt-p",
+            Action::Scratchpads => "ctrl-o",
+            Action::NewScratchpad => "ctrl-n",
+            Action::SaveAs => "ctrl-shift-s",
+            Action::OpenFile => "ctrl-shift-o",
+            Action::NextBuffer => "ctrl-pagedown",
+            Action::PreviousBuffer => "ctrl-pageup",
+            Action::CloseBuffer => "ctrl-w",
+            Action::ToggleSidebar => "ctrl-b",
+            Action::GoToInclude => "ctrl-g",
+            Action::GoBack => "alt-g",
+            Action::ToggleExpandedSource => "ctrl-e",
+            Action::EmbeddedDiagrams => "ctrl-l",
+            Action::ZoomIn => "alt-up",
+            Action::ZoomOut => "alt-down",
+        }
+    }
+}
+
+/// One key or several keys, e.g. `"ctrl-c"` or `["ctrl-c", "ctrl-q"]`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyBinding {
+    pub fn keys(&self) -> &[String] {
+        match self {
+            KeyBinding::One(key) => std::slice::from_ref(key),
+            KeyBinding::Many(keys) => keys
+        }
+    }
+}
+
+pub fn default_keybindings() -> BTreeMap<Action, KeyBinding> {
+    Action::iter()
+        .map(|action| (action, KeyBinding::One(action.default_keys().to_string())))
+        .collect()
+}
+
+impl Config {
+    /// This configuration overridden by the table of a project configuration, whose paths are relative to the given directory
+    pub fn with_overrides(&self, overrides: &toml::Table, base_dir_path: &Path) -> anyhow::Result<Config> {
+        let mut overrides = overrides.clone();
+
+        let mut plantuml_options = match overrides.remove("plantuml") {
+            Some(plantuml) => plantuml.try_into::<PlantUmlOptions>()?,
+            None => PlantUmlOptions::default()
+        };
+
+        plantuml_options.resolve_paths(base_dir_path);
+
+        let mut table = toml::Table::try_from(self)?;
+        merge_config_tables(&mut table, &overrides);
+
+        let mut config: Config = table.try_into()?;
+        config.plantuml = self.plantuml.clone().merge(&plantuml_options);
+        config.key_combinations()?;
+
+        Ok(config)
+    }
+
+    /// Key combinations of every action, the configured ones replacing the defaults
+    pub fn key_combinations(&self) -> anyhow::Result<HashMap<KeyCombination, Action>> {
+        let mut key_combinations = HashMap::new();
+        let mut keybindings = default_keybindings();
+        keybindings.extend(self.keybindings.clone());
+
+        for (action, keybinding) in keybindings {
+            for key in keybinding.keys() {
+                let key_combination = crokey::parse(key).map_err(|err| anyhow!("Invalid key \"{}\" in the configuration: {}", key, err))?;
+                key_combinations.insert(key_combination, action);
+            }
+        }
+
+        Ok(key_combinations)
+    }
+}
+
+pub struct LoadedConfig {
+    pub config: Config,
+    pub path: PathBuf,
+    /// Content of the configuration file, if any
+    pub file_table: Option<toml::Table>,
+}
+
+fn load_config() -> anyhow::Result<LoadedConfig> {
+    let path = get_config_dir().join(CONFIG_FILE_NAME);
+
+    let file_table = match path.is_file() {
+        true => {
+            let content = fs::read_to_string(&path)?;
+            let file_table = content
+                .parse::<toml::Table>()
+                .map_err(|err| anyhow!("Invalid configuration \"{}\"\n{}", path.display(), err))?;
+
+            Some(file_table)
+        },
+        false => None
+    };
+
+    let mut table = default_table();
+
+    if let Some(file_table) = &file_table {
+        merge_config_tables(&mut table, file_table);
+    }
+
+    if let Some(env_table) = env_table() {
+        merge_config_tables(&mut table, &env_table);
+    }
+
+    let mut config: Config = table
+        .try_into()
+        .map_err(|err| anyhow!("Invalid configuration \"{}\"\n{}", path.display(), err))?;
+
+    config.plantuml.resolve_paths(&get_config_dir());
+
+    config.key_combinations()?;
+    config.renderer.command_args()?;
+
+    Ok(LoadedConfig {
+        config,
+        path,
+        file_table,
+    })
+}
+
+/// Reports an invalid configuration file, which would otherwise be silently replaced by the defaults
+pub fn validate_config() -> anyhow::Result<()> {
+    match &*LOADED_CONFIG {
+        Ok(_) => Ok(()),
+        Err(err) => Err(anyhow!("{err:#}"))
+    }
+}
+
+fn default_table() -> toml::Table {
+    let config = Config {
+        keybindings: default_keybindings(),
+        ..Config::default()
+    };
+
+    toml::Table::try_from(config).unwrap_or_default()
+}
+
+/// The `PLANT_UML` env var overrides the configured command
+fn env_table() -> Option<toml::Table> {
+    let command = env::var("PLANT_UML").ok().filter(|command| !command.trim().is_empty())?;
+
+    let mut renderer = toml::Table::new();
+    renderer.insert(String::from("command"), toml::Value::String(command));
+
+    let mut table = toml::Table::new();
+    table.insert(String::from("renderer"), toml::Value::Table(renderer));
+
+    Some(table)
+}
+
+/// Settings given on the command line, only used to report the effective configuration
+fn cli_table() -> toml::Table {
+    let mut table = toml::Table::new();
+
+    if ARGS.light_mode || ARGS.dark_mode {
+        let mut ui = toml::Table::new();
+        ui.insert(String::from("dark-mode"), toml::Value::Boolean(!ARGS.light_mode && ARGS.dark_mode));
+        table.insert(String::from("ui"), toml::Value::Table(ui));
+    }
+
+    if let Ok(plantuml) = toml::Table::try_from(ARGS.plantuml_options()) {
+        let plantuml = plantuml
+            .into_iter()
+            .filter(|(_, value)| !matches!(value, toml::Value::Array(array) if array.is_empty()))
+            .filter(|(_, value)| !matches!(value, toml::Value::Table(table) if table.is_empty()))
+            .collect::<toml::Table>();
+
+        if !plantuml.is_empty() {
+            table.insert(String::from("plantuml"), toml::Value::Table(plantuml));
+        }
+    }
+
+    table
+}
+
+/// Merges the overrides into the configuration table, the lists of PlantUML options being appended as `PlantUmlOptions::merge` does
+fn merge_config_tables(table: &mut toml::Table, overrides: &toml::Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(overrides)) => merge_tables(table, overrides, key == "plantuml"),
+            _ => {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Deeply merges the overrides into the table, replacing the arrays or appending to them
+fn merge_tables(table: &mut toml::Table, overrides: &toml::Table, append_arrays: bool) {
+    for (key, value) in overrides {
+        match (table.get_mut(key), value) {
+            (Some(toml::Value::Table(table)), toml::Value::Table(overrides)) => merge_tables(table, overrides, append_arrays),
+            (Some(toml::Value::Array(array)), toml::Value::Array(overrides)) if append_arrays => array.extend(overrides.iter().cloned()),
+            _ => {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// A setting of the effective configuration and where its value comes from
+#[derive(Serialize)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub source: String,
+}
+
+/// Every setting of the configuration merged from the defaults, the configuration file, the environment, the project configuration and the command line
+pub fn config_entries(project_config: &ProjectConfig) -> anyhow::Result<(PathBuf, Vec<ConfigEntry>)> {
+    let loaded_config = LOADED_CONFIG.as_ref().map_err(|err| anyhow!("{err:#}"))?;
+
+    let mut layers = vec![(String::from("default"), default_table())];
+
+    if let Some(file_table) = &loaded_config.file_table {
+        layers.push((format!("file {}", loaded_config.path.display()), file_table.clone()));
+    }
+
+    if let Some(env_table) = env_table() {
+        layers.push((String::from("env PLANT_UML"), env_table));
+    }
+
+    if let Some(project_path) = &project_config.path {
+        layers.push((format!("project {}", project_path.display()), project_config.table.clone()));
+    }
+
+    layers.push((String::from("command line"), cli_table()));
+
+    let mut table = toml::Table::new();
+    let mut sources = HashMap::new();
+
+    for (source, layer) in layers {
+        merge_config_tables(&mut table, &layer);
+
+        for (key, _) in flatten_table(&layer, "") {
+            sources.insert(key, source.clone());
+        }
+    }
+
+    let entries = flatten_table(&table, "")
+        .into_iter()
+        .map(|(key, value)| ConfigEntry {
+            source: sources.get(&key).cloned().unwrap_or_else(|| String::from("default")),
+            key,
+            value: value.to_string(),
+        })
+        .collect();
+
+    Ok((loaded_config.path.clone(), entries))
+}
+
+/// Dotted keys of the leaves of the table, e.g. `renderer.command`
+fn flatten_table(table: &toml::Table, prefix: &str) -> Vec<(String, toml::Value)> {
+    let mut leaves = vec![];
+
+    for (key, value) in table {
+        let key = match prefix.is_empty() {
+            true => key.clone(),
+            false => format!("{prefix}.{key}")
+        };
+
+        match value {
+            toml::Value::Table(table) => leaves.extend(flatten_table(table, &key)),
+            value => leaves.push((key, value.clone()))
+        }
+    }
+
+    leaves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_every_action_to_its_own_default_key() {
+        let key_combinations = Config::default().key_combinations().unwrap();
+
+        assert_eq!(key_combinations.len(), Action::iter().count());
+    }
+}
@@ -0,0 +1,10 @@
+use crate::app::APP_NAME;
+use directories::BaseDirs;
+use std::path::PathBuf;
+
+/// Directory of the user configuration files, which may not exist
+pub fn get_config_dir() -> PathBuf {
+    let base_dir = BaseDirs::new().unwrap();
+
+    base_dir.config_dir().join(APP_NAME.to_lowercase())
+}
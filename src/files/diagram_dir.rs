@@ -0,0 +1,36 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions of the files listed in the sidebar
+pub const DIAGRAM_EXTENSIONS: [&str; 4] = ["puml", "pmu", "plantuml", "iuml"];
+
+pub fn is_diagram_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| DIAGRAM_EXTENSIONS.iter().any(|diagram_extension| extension.eq_ignore_ascii_case(diagram_extension)))
+}
+
+/// Diagram files under the directory and its subdirectories, sorted by path. Hidden directories such as `.git` are skipped.
+pub fn list_diagram_files(dir_path: &Path) -> Vec<PathBuf> {
+    let mut file_paths = vec![];
+    let mut dir_paths = vec![dir_path.to_path_buf()];
+
+    while let Some(dir_path) = dir_paths.pop() {
+        let Ok(entries) = fs::read_dir(&dir_path) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() && !is_hidden => dir_paths.push(path),
+                Ok(file_type) if !file_type.is_dir() && is_diagram_file(&path) => file_paths.push(path),
+                _ => {}
+            }
+        }
+    }
+
+    file_paths.sort();
+    file_paths
+}
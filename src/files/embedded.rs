@@ -0,0 +1,393 @@
+use crate::files::pmu::{split_diagram_blocks, DiagramBlock};
+use anyhow::bail;
+use std::path::Path;
+
+const MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+const ASCIIDOC_EXTENSIONS: [&str; 3] = ["adoc", "asciidoc", "asc"];
+/// Languages of the Markdown fenced blocks holding a diagram, e.g. ```` ```plantuml ````
+const MARKDOWN_LANGUAGES: [&str; 2] = ["plantuml", "puml"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DocumentKind {
+    Markdown,
+    AsciiDoc,
+}
+
+/// Diagram written inside a Markdown or AsciiDoc document
+#[derive(Clone)]
+pub struct EmbeddedBlock {
+    /// Name given in the AsciiDoc block attributes, e.g. `[plantuml, sequence, svg]`
+    pub id: Option<String>,
+    /// Index of the first line of the diagram in the document, after the opening delimiter
+    pub start_line: usize,
+    /// Index of the closing delimiter, the line count when the block is left open
+    pub end_line: usize,
+    pub source: String,
+}
+
+impl EmbeddedBlock {
+    /// Name of the diagram, its title or first line otherwise
+    pub fn label(&self) -> String {
+        if let Some(id) = &self.id {
+            return id.clone();
+        }
+
+        let lines = self.source
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('\''));
+
+        let start_id = lines.clone().find_map(|line| line.strip_prefix("@start")).and_then(|line| line.split_once(' ')).map(|(_, id)| id.trim());
+        let title = lines.clone().find_map(|line| line.strip_prefix("title "));
+
+        start_id
+            .or(title)
+            .or(lines.clone().find(|line| !line.starts_with('@')))
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// Document whose diagrams are edited one at a time
+pub struct EmbeddedDocument {
+    pub kind: DocumentKind,
+    /// Content of the document when it was last loaded or saved
+    pub text: String,
+    pub blocks: Vec<EmbeddedBlock>,
+    /// Block shown in the editor
+    pub block_index: usize,
+}
+
+impl EmbeddedDocument {
+    pub fn new(document_path: &Path, kind: DocumentKind, text: String, block_index: usize) -> anyhow::Result<EmbeddedDocument> {
+        let blocks = find_embedded_blocks(kind, &text);
+
+        if blocks.is_empty() {
+            bail!("\"{}\" holds no PlantUML diagram", document_path.display());
+        }
+
+        Ok(EmbeddedDocument {
+            kind,
+            text,
+            block_index: block_index.min(blocks.len() - 1),
+            blocks,
+        })
+    }
+
+    pub fn block(&self) -> &EmbeddedBlock {
+        &self.blocks[self.block_index]
+    }
+
+    /// Document text with the source of the current block replaced, refused when the source would end the block early
+    pub fn text_with_block_source(&self, document_path: &Path, source: &str) -> anyhow::Result<String> {
+        let text = replace_block_source(&self.text, self.block(), source);
+        let start_line = self.block().start_line;
+
+        let is_block_kept = find_embedded_blocks(self.kind, &text)
+            .iter()
+            .any(|block| block.start_line == start_line && block.source.lines().eq(source.lines()));
+
+        if !is_block_kept {
+            bail!("The diagram holds a line closing its block in \"{}\"", document_path.display());
+        }
+
+        Ok(text)
+    }
+
+    /// Takes the text changed by another editor, finding the edited block again by its source, the closest one to where it was
+    pub fn reload(&mut self, document_path: &Path, text: String) -> anyhow::Result<()> {
+        let blocks = find_embedded_blocks(self.kind, &text);
+        let source = &self.block().source;
+
+        let block_index = blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.source == *source)
+            .min_by_key(|(index, _)| index.abs_diff(self.block_index))
+            .map(|(index, _)| index);
+
+        let Some(block_index) = block_index else {
+            bail!("\"{}\" was changed by another editor, the diagram being changed or removed there", document_path.display());
+        };
+
+        self.text = text;
+        self.blocks = blocks;
+        self.block_index = block_index;
+
+        Ok(())
+    }
+
+    /// Takes the saved text as the new content, the edited block starting on the same line
+    pub fn set_saved_text(&mut self, text: String) {
+        let blocks = find_embedded_blocks(self.kind, &text);
+        let start_line = self.block().start_line;
+
+        if let Some(block_index) = blocks.iter().position(|block| block.start_line == start_line) {
+            self.text = text;
+            self.blocks = blocks;
+            self.block_index = block_index;
+        }
+    }
+}
+
+pub fn document_kind(path: &Path) -> Option<DocumentKind> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+
+    match (MARKDOWN_EXTENSIONS.contains(&extension.as_str()), ASCIIDOC_EXTENSIONS.contains(&extension.as_str())) {
+        (true, _) => Some(DocumentKind::Markdown),
+        (false, true) => Some(DocumentKind::AsciiDoc),
+        (false, false) => None
+    }
+}
+
+/// Diagrams of a document: ```` ```plantuml ```` fenced blocks in Markdown, `[plantuml]` listing or literal blocks in AsciiDoc
+pub fn find_embedded_blocks(kind: DocumentKind, text: &str) -> Vec<EmbeddedBlock> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut blocks = vec![];
+    let mut index = 0;
+
+    while index < lines.len() {
+        let opening = match kind {
+            DocumentKind::Markdown => markdown_fence(lines[index]).map(|(fence, language)| (fence, None, MARKDOWN_LANGUAGES.contains(&language.as_str()))),
+            DocumentKind::AsciiDoc => match (asciidoc_attributes(lines[index]), lines.get(index + 1).and_then(|line| asciidoc_delimiter(line))) {
+                (Some(id), Some(delimiter)) => {
+                    // The delimiter follows the attributes
+                    index += 1;
+                    Some((delimiter, id, true))
+                },
+                _ => asciidoc_delimiter(lines[index]).map(|delimiter| (delimiter, None, false))
+            }
+        };
+
+        let Some((delimiter, id, is_diagram)) = opening else {
+            index += 1;
+            continue;
+        };
+
+        let start_line = index + 1;
+        let end_line = (start_line..lines.len())
+            .find(|line_index| is_closing_delimiter(kind, lines[*line_index], &delimiter))
+            .unwrap_or(lines.len());
+
+        if is_diagram {
+            let mut source = lines[start_line..end_line].join("\n");
+
+            if !source.is_empty() {
+                source.push('\n');
+            }
+
+            blocks.push(EmbeddedBlock { id, start_line, end_line, source });
+        }
+
+        index = end_line + 1;
+    }
+
+    blocks
+}
+
+/// Diagram blocks to render, numbered after their line in the document
+pub fn embedded_diagram_blocks(kind: DocumentKind, text: &str) -> Vec<DiagramBlock> {
+    find_embedded_blocks(kind, text)
+        .into_iter()
+        .map(|block| {
+            let (source, line_offset) = wrap_diagram_source(&block.source);
+
+            DiagramBlock {
+                // A Markdown diagram is named after its `@startuml` line
+                id: block.id.or_else(|| split_diagram_blocks(&source).into_iter().next().and_then(|diagram_block| diagram_block.id)),
+                start_line: block.start_line - line_offset,
+                source,
+            }
+        })
+        .collect()
+}
+
+/// Source of a block as PlantUML expects it, wrapped in `@startuml` and `@enduml` when the document leaves them out.
+/// Also returns the number of lines added before the source.
+pub fn wrap_diagram_source(source: &str) -> (String, usize) {
+    match source.lines().any(|line| line.trim_start().starts_with("@start")) {
+        true => (source.to_string(), 0),
+        false => (format!("@startuml\n{source}@enduml\n"), 1)
+    }
+}
+
+/// Document text with the lines of the block replaced, keeping its line endings
+pub fn replace_block_source(text: &str, block: &EmbeddedBlock, source: &str) -> String {
+    let line_ending = match text.contains("\r\n") {
+        true => "\r\n",
+        false => "\n"
+    };
+
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut new_lines = lines[..block.start_line.min(lines.len())].to_vec();
+
+    new_lines.extend(source.lines());
+    new_lines.extend(&lines[block.end_line.min(lines.len())..]);
+
+    let mut new_text = new_lines.join(line_ending);
+
+    if text.ends_with('\n') {
+        new_text.push_str(line_ending);
+    }
+
+    new_text
+}
+
+/// Backticks or tildes opening a Markdown fenced block, with the language of the block
+fn markdown_fence(line: &str) -> Option<(String, String)> {
+    let trimmed = line.trim_start();
+    let fence_char = trimmed.chars().next().filter(|char| *char == '`' || *char == '~')?;
+    let fence_length = trimmed.chars().take_while(|char| *char == fence_char).count();
+
+    if fence_length < 3 {
+        return None;
+    }
+
+    let info = trimmed[fence_length..].trim();
+    // Some renderers take the language between braces, e.g. ```` ```{plantuml} ````
+    let language = info
+        .trim_start_matches(['{', '.'])
+        .split(|char: char| char.is_whitespace() || char == '}' || char == ',')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    Some((trimmed[..fence_length].to_string(), language))
+}
+
+/// Name given in a `[plantuml, name, format]` attribute line, which opens a diagram block
+fn asciidoc_attributes(line: &str) -> Option<Option<String>> {
+    let attributes = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let mut attributes = attributes.split(',').map(str::trim);
+
+    if attributes.next() != Some("plantuml") {
+        return None;
+    }
+
+    let id = attributes
+        .next()
+        .filter(|id| !id.is_empty() && !id.contains('='))
+        .map(String::from);
+
+    Some(id)
+}
+
+/// `----` or `....` delimiting an AsciiDoc listing or literal block
+fn asciidoc_delimiter(line: &str) -> Option<String> {
+    let trimmed = line.trim_end();
+
+    match trimmed.len() >= 4 && (trimmed.chars().all(|char| char == '-') || trimmed.chars().all(|char| char == '.')) {
+        true => Some(trimmed.to_string()),
+        false => None
+    }
+}
+
+fn is_closing_delimiter(kind: DocumentKind, line: &str, delimiter: &str) -> bool {
+    match kind {
+        // The closing fence is at least as long as the opening one
+        DocumentKind::Markdown => {
+            let trimmed = line.trim();
+            let fence_char = delimiter.chars().next().unwrap_or('`');
+
+            trimmed.len() >= delimiter.len() && trimmed.chars().all(|char| char == fence_char)
+        },
+        DocumentKind::AsciiDoc => line.trim_end() == delimiter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_markdown_blocks() {
+        let text = "# Doc\n\n```plantuml\nA -> B\n```\n\n```js\nx\n```\n\n~~~puml\nclass Foo\n~~~\n";
+        let blocks = find_embedded_blocks(DocumentKind::Markdown, text);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!((blocks[0].start_line, blocks[0].end_line, blocks[0].source.as_str()), (3, 4, "A -> B\n"));
+        assert_eq!((blocks[1].start_line, blocks[1].end_line, blocks[1].source.as_str()), (11, 12, "class Foo\n"));
+    }
+
+    #[test]
+    fn skips_shorter_fences_inside_longer_ones() {
+        let text = "````markdown\n```plantuml\nA -> B\n```\n````\n\n````plantuml\nnote: ```\n```\nA -> B\n````\n";
+        let blocks = find_embedded_blocks(DocumentKind::Markdown, text);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (7, 10));
+        assert_eq!(blocks[0].source, "note: ```\n```\nA -> B\n");
+    }
+
+    #[test]
+    fn takes_an_unclosed_block_up_to_the_end() {
+        let text = "```plantuml\nA -> B\nB -> C";
+        let blocks = find_embedded_blocks(DocumentKind::Markdown, text);
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (1, 3));
+        assert_eq!(blocks[0].source, "A -> B\nB -> C\n");
+    }
+
+    #[test]
+    fn finds_asciidoc_blocks() {
+        let text = "= Doc\n\n[plantuml, flow, svg]\n----\nA -> B\n----\n\n----\nnot a diagram\n----\n\n[plantuml]\n....\n@startuml\nC -> D\n@enduml\n....\n";
+        let blocks = find_embedded_blocks(DocumentKind::AsciiDoc, text);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].id.as_deref(), Some("flow"));
+        assert_eq!((blocks[0].start_line, blocks[0].end_line, blocks[0].source.as_str()), (4, 5, "A -> B\n"));
+        assert_eq!(blocks[1].id, None);
+        assert_eq!((blocks[1].start_line, blocks[1].end_line), (13, 16));
+        assert_eq!(blocks[1].source, "@startuml\nC -> D\n@enduml\n");
+    }
+
+    #[test]
+    fn replaces_a_block_source() {
+        let text = "Intro\n```plantuml\nA -> B\n```\nEnd\n";
+        let block = &find_embedded_blocks(DocumentKind::Markdown, text)[0];
+
+        assert_eq!(replace_block_source(text, block, "A -> B\nB -> C\n"), "Intro\n```plantuml\nA -> B\nB -> C\n```\nEnd\n");
+        assert_eq!(replace_block_source(text, block, ""), "Intro\n```plantuml\n```\nEnd\n");
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let text = "Intro\r\n```plantuml\r\nA -> B\r\n```\r\nEnd\r\n";
+        let blocks = find_embedded_blocks(DocumentKind::Markdown, text);
+
+        assert_eq!(blocks[0].source, "A -> B\n");
+        assert_eq!(replace_block_source(text, &blocks[0], "C -> D\n"), "Intro\r\n```plantuml\r\nC -> D\r\n```\r\nEnd\r\n");
+    }
+
+    #[test]
+    fn replaces_an_unclosed_block_source() {
+        let text = "```plantuml\nA -> B";
+        let block = &find_embedded_blocks(DocumentKind::Markdown, text)[0];
+
+        assert_eq!(replace_block_source(text, block, "C -> D\n"), "```plantuml\nC -> D");
+    }
+
+    #[test]
+    fn finds_the_edited_block_again_after_an_external_change() {
+        let path = Path::new("doc.md");
+        let text = "```plantuml\nA -> B\n```\n\n```plantuml\nC -> D\n```\n";
+        let mut document = EmbeddedDocument::new(path, DocumentKind::Markdown, text.to_string(), 1).unwrap();
+
+        document.reload(path, format!("```plantuml\nX -> Y\n```\n\n{text}")).unwrap();
+        assert_eq!(document.block_index, 2);
+        assert_eq!(document.block().source, "C -> D\n");
+
+        assert!(document.reload(path, String::from("No diagram anymore\n")).is_err());
+        assert!(document.reload(path, String::from("```plantuml\nC -> E\n```\n")).is_err());
+    }
+
+    #[test]
+    fn refuses_a_source_closing_its_block() {
+        let path = Path::new("doc.md");
+        let document = EmbeddedDocument::new(path, DocumentKind::Markdown, String::from("```plantuml\nA -> B\n```\n"), 0).unwrap();
+
+        assert!(document.text_with_block_source(path, "A -> B\n```\n").is_err());
+        assert!(document.text_with_block_source(path, "A -> C\n").is_ok());
+    }
+}
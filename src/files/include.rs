@@ -0,0 +1,100 @@
+use plantuml_parser::{PathResolver, PlantUmlLine, PlantUmlLineKind};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File of a local `!include`, resolved from the including file like `plantuml_parser`'s path resolver.
+/// None for the standard library, remote and preprocessed includes, which can't be followed locally.
+pub fn resolve_include(file_path: &Path, include_path: &str) -> Option<PathBuf> {
+    if include_path.starts_with('<') || include_path.contains("://") || include_path.contains(['$', '%']) {
+        return None;
+    }
+
+    let mut path_resolver = PathResolver::new(file_path);
+    path_resolver.add(PathBuf::from(include_path));
+
+    path_resolver.build().ok()
+}
+
+/// Path given to the `!include` of the line, if any
+pub fn line_include_path(line: &str) -> Option<String> {
+    let (_, (_, plantuml_line)) = PlantUmlLine::parse(line.into()).ok()?;
+
+    match plantuml_line.kind() {
+        PlantUmlLineKind::Include(include_line) => Some(include_line.token().filepath().to_string()),
+        _ => None
+    }
+}
+
+/// Existing files included by the text, directly or through other included files
+pub fn included_file_paths(file_path: &Path, text: &str) -> Vec<PathBuf> {
+    let mut included_file_paths = vec![];
+    let mut visited_file_paths = HashSet::from([file_path.to_path_buf()]);
+    let mut pending = vec![(file_path.to_path_buf(), text.to_string())];
+
+    while let Some((file_path, text)) = pending.pop() {
+        for include_path in text.lines().filter_map(line_include_path) {
+            let Some(included_file_path) = resolve_include(&file_path, &include_path) else {
+                continue;
+            };
+
+            if !included_file_path.is_file() || !visited_file_paths.insert(included_file_path.clone()) {
+                continue;
+            }
+
+            if let Ok(included_text) = fs::read_to_string(&included_file_path) {
+                pending.push((included_file_path.clone(), included_text));
+            }
+
+            included_file_paths.push(included_file_path);
+        }
+    }
+
+    included_file_paths
+}
+
+/// Line of a diagram with its includes inlined, knowing where it comes from
+#[derive(Clone)]
+pub struct ExpandedLine {
+    pub text: String,
+    pub file_path: PathBuf,
+    /// 0-based line in the file
+    pub line: usize,
+}
+
+/// Lines of the text with every local `!include` replaced by the lines of the included file, recursively.
+/// The `@start`/`@end` lines of the included files are left out, as PlantUML does, and an include cycle keeps the `!include` line.
+pub fn inline_includes(file_path: &Path, text: &str) -> Vec<ExpandedLine> {
+    let mut expanded_lines = vec![];
+    inline_file_includes(file_path, text, &mut vec![file_path.to_path_buf()], &mut expanded_lines);
+
+    expanded_lines
+}
+
+fn inline_file_includes(file_path: &Path, text: &str, including_file_paths: &mut Vec<PathBuf>, expanded_lines: &mut Vec<ExpandedLine>) {
+    let is_included = including_file_paths.len() > 1;
+
+    for (line, line_text) in text.lines().enumerate() {
+        if is_included && line_text.trim_start().starts_with('@') {
+            continue;
+        }
+
+        let included_file = line_include_path(line_text)
+            .and_then(|include_path| resolve_include(file_path, &include_path))
+            .filter(|included_file_path| !including_file_paths.contains(included_file_path))
+            .and_then(|included_file_path| Some((fs::read_to_string(&included_file_path).ok()?, included_file_path)));
+
+        match included_file {
+            Some((included_text, included_file_path)) => {
+                including_file_paths.push(included_file_path.clone());
+                inline_file_includes(&included_file_path, &included_text, including_file_paths, expanded_lines);
+                including_file_paths.pop();
+            },
+            None => expanded_lines.push(ExpandedLine {
+                text: line_text.to_string(),
+                file_path: file_path.to_path_buf(),
+                line,
+            })
+        }
+    }
+}
@@ -1,3 +1,14 @@
+pub mod atomic_write;
+pub mod color_scheme;
+pub mod config;
+pub mod config_dir;
 pub mod data_dir;
+pub mod diagram_dir;
+pub mod embedded;
+pub mod include;
 pub mod pmu;
+pub mod project_config;
+pub mod scratchpad;
+pub mod session;
+pub mod swap;
 pub mod utils;
\ No newline at end of file
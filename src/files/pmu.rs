@@ -1,9 +1,16 @@
 use std::fs;
-use crate::app::App;
-use crate::args::ARGS;
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+use crate::app::{App, Buffer};
+use crate::args::{input_dir_path, ARGS};
+use crate::files::diagram_dir::list_diagram_files;
+use crate::files::atomic_write::write_atomically;
+use crate::files::color_scheme::ColorScheme;
+use crate::files::embedded::{document_kind, EmbeddedDocument};
+use crate::files::project_config::load_project_config;
+use crate::files::scratchpad::{is_scratchpad, latest_scratchpad_path, scratchpad_path};
+use crate::files::session::{load_file_session, FileSession};
+use crate::logic::area_separation::clamp_editor_percentage;
+use crate::logic::zoom::{clamp_zoom, DEFAULT_ZOOM};
+use std::path::{Path, PathBuf};
 use clap::ValueEnum;
 use plantuml_parser::{PlantUmlLine, PlantUmlLineKind};
 use ratatui::prelude::{Line, Stylize};
@@ -18,77 +25,199 @@ Alice -> Bob: Hello
 @enduml";
 
 impl App<'_> {
-    pub fn save_pmu_file(&mut self) -> anyhow::Result<()> {
-        let diagram = &self.text_input.text;
-        let temp_path = self.input_file_path.with_extension("~");
-
-        let mut input_file = File::options()
-            .create(true)
-            .write(true)
-            .read(true)
-            .truncate(true)
-            .open(&temp_path)
-            .expect("Failed to open input file");
-
-        input_file.write_all(diagram.as_bytes())?;
-        
-        fs::copy(&temp_path, &self.input_file_path)?;
-        fs::remove_file(&temp_path)?;
+    /// Loads the file with its project configuration and session into a new buffer
+    pub fn load_buffer<'b>(&self, input_file_path: PathBuf) -> anyhow::Result<Buffer<'b>> {
+        let project_config = load_project_config(&input_file_path)?;
+        let file_session = match ARGS.no_session {
+            true => FileSession::default(),
+            false => load_file_session(&self.data_dir, &input_file_path)
+        };
+
+        let saved_text = match fs::exists(&input_file_path)? {
+            true => Some(fs::read_to_string(&input_file_path)?),
+            false => None
+        };
+
+        let mut buffer = Buffer::new(input_file_path);
+
+        match document_kind(&buffer.input_file_path) {
+            // A document is edited one diagram at a time
+            Some(kind) => {
+                let document = EmbeddedDocument::new(&buffer.input_file_path, kind, saved_text.unwrap_or_default(), file_session.embedded_diagram.unwrap_or(0))?;
+
+                buffer.text_input.text = document.block().source.clone();
+                buffer.saved_text = document.block().source.clone();
+                buffer.embedded = Some(document);
+            },
+            None => {
+                buffer.text_input.text = saved_text.clone().unwrap_or_else(|| DEFAULT_DIAGRAM.to_string());
+                buffer.saved_text = saved_text.unwrap_or_default();
+            }
+        }
+
+        // The file may have changed since the session was saved
+        buffer.text_input.set_cursor_position(file_session.cursor_position);
+        buffer.text_input.scroll_offset = file_session.scroll_offset;
+
+        buffer.project_config = project_config;
+        buffer.file_session = file_session;
+
+        Ok(buffer)
+    }
+
+    /// Uses the key bindings of the current buffer project
+    pub fn apply_project_config(&mut self) -> anyhow::Result<()> {
+        self.key_combinations = self.buffer.project_config.config.key_combinations()?;
+
+        Ok(())
+    }
+
+    /// Restores the mode, split and zoom remembered for the file of the current buffer, on open
+    pub fn restore_file_session(&mut self) {
+        let file_session = &self.buffer.file_session;
+        let ui_config = &self.buffer.project_config.config.ui;
+
+        self.dark_mode = match (ARGS.light_mode, ARGS.dark_mode) {
+            (false, false) => file_session.dark_mode.unwrap_or(ui_config.dark_mode),
+            (light_mode, dark_mode) => !light_mode || dark_mode
+        };
+        self.editor_percentage = clamp_editor_percentage(file_session.split.unwrap_or(ui_config.split));
+        self.zoom = clamp_zoom(file_session.zoom.unwrap_or(DEFAULT_ZOOM));
+    }
+
+    /// Replaces the file of the current buffer, the previous one being left with its session saved
+    pub fn open_pmu_file(&mut self, input_file_path: PathBuf) -> anyhow::Result<()> {
+        self.close_swap_file();
+        self.save_session()?;
+
+        let buffer = self.load_buffer(input_file_path)?;
+
+        self.buffer.cancellation_token.cancel();
+        self.buffer = buffer;
+
+        self.apply_project_config()?;
+        self.restore_file_session();
+        self.check_swap_file();
 
         Ok(())
     }
+
+    /// Saves the text on every change when autosaving, the preview being rendered from the text itself
+    pub fn autosave_pmu_file(&mut self) {
+        if self.autosave() {
+            self.save_pmu_file();
+        }
+    }
+
+    /// Scratchpads are always saved, having no other copy
+    pub fn autosave(&self) -> bool {
+        self.buffer.project_config.config.editor.autosave || is_scratchpad(&self.data_dir, &self.buffer.input_file_path)
+    }
+
+    /// Saves the text, a failure being shown in the UI rather than stopping the TUI, and returns whether it succeeded.
+    /// The diagram of a document is written back into it.
+    pub fn save_pmu_file(&mut self) -> bool {
+        let result = self.file_text().and_then(|text| {
+            write_atomically(&self.buffer.input_file_path, text.as_bytes())?;
+            Ok(text)
+        });
+
+        match result {
+            Ok(text) => {
+                if let Some(document) = self.buffer.embedded.as_mut() {
+                    document.set_saved_text(text);
+                }
+
+                self.buffer.saved_text = self.buffer.text_input.text.clone();
+                self.buffer.save_error = None;
+                true
+            },
+            Err(err) => {
+                self.buffer.save_error = Some(format!("{err:#}"));
+                false
+            }
+        }
+    }
+
+    /// Content written to the file, the document holding the diagram for an embedded one
+    fn file_text(&mut self) -> anyhow::Result<String> {
+        let Some(document) = self.buffer.embedded.as_mut() else {
+            return Ok(self.buffer.text_input.text.clone());
+        };
+
+        // Keeps the changes made to the rest of the document in another editor
+        if let Ok(document_text) = fs::read_to_string(&self.buffer.input_file_path)
+            && document_text != document.text {
+            document.reload(&self.buffer.input_file_path, document_text)?;
+        }
+
+        document.text_with_block_source(&self.buffer.input_file_path, &self.buffer.text_input.text)
+    }
 }
 
-pub fn get_input_file_path(data_dir: &PathBuf) -> anyhow::Result<PathBuf> {
-    match &ARGS.input {
-        None => {
-            let path = data_dir.join("temp");
-            fs::remove_file(&path).ok();
-            Ok(path)
-        },
-        Some(path) => Ok(path.clone()),
+/// Files to open, each in its own buffer, falling back to the first diagram of the input directory, then to a scratchpad in the TUI
+pub fn get_input_file_paths(data_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let input_file_paths = ARGS.inputs
+        .iter()
+        .filter(|input_path| !input_path.is_dir())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if !input_file_paths.is_empty() {
+        return Ok(input_file_paths);
+    }
+
+    if let Some(input_file_path) = input_dir_path().and_then(|input_dir_path| list_diagram_files(input_dir_path).into_iter().next()) {
+        return Ok(vec![input_file_path]);
+    }
+
+    // The subcommands work on their own files, without creating a scratchpad
+    match (&ARGS.command, &ARGS.scratch) {
+        (Some(_), _) => Ok(vec![]),
+        (None, Some(name)) => Ok(vec![scratchpad_path(data_dir, name)]),
+        (None, None) => Ok(vec![latest_scratchpad_path(data_dir)]),
     }
 }
 
-pub fn pmu_to_paragraph<'a>(text: &str) -> Paragraph<'a> {
-    let lines = syntax_highlighting(text);
+pub fn pmu_to_paragraph<'a>(text: &str, color_scheme: &ColorScheme) -> Paragraph<'a> {
+    let lines = syntax_highlighting(text, color_scheme);
     Paragraph::new(lines)
 }
 
-fn syntax_highlighting<'a>(input: &str) -> Vec<Line<'a>> {
+fn syntax_highlighting<'a>(input: &str, color_scheme: &ColorScheme) -> Vec<Line<'a>> {
     let mut lines = vec![];
     let mut in_comment_block = false;
 
     for line in input.lines() {
         if let Ok((_rest, (text_line, plantuml_line))) = PlantUmlLine::parse(line.into()) {
             let mut line = match plantuml_line.kind() {
-                PlantUmlLineKind::Start(_) => Line::raw(plantuml_line.raw_str().to_string()).blue(),
-                PlantUmlLineKind::End(_) => Line::raw(plantuml_line.raw_str().to_string()).blue(),
+                PlantUmlLineKind::Start(_) => Line::raw(plantuml_line.raw_str().to_string()).fg(color_scheme.start_end),
+                PlantUmlLineKind::End(_) => Line::raw(plantuml_line.raw_str().to_string()).fg(color_scheme.start_end),
                 PlantUmlLineKind::BlockCommentOpen(_) => {
                     in_comment_block = true;
                     Line::raw(text_line.to_string())
                 },
                 PlantUmlLineKind::BlockCommentClose(_) => {
                     in_comment_block = false;
-                    Line::raw(text_line.to_string()).dim()
+                    Line::raw(text_line.to_string()).fg(color_scheme.comment)
                 },
-                PlantUmlLineKind::InComment(_) => Line::raw(text_line.to_string()).dim(),
-                PlantUmlLineKind::Include(_) => Line::raw(plantuml_line.raw_str().to_string()).yellow(),
-                PlantUmlLineKind::Title(_) => Line::raw(plantuml_line.raw_str().to_string()).cyan(),
-                PlantUmlLineKind::Header(_) => Line::raw(plantuml_line.raw_str().to_string()).magenta(),
-                PlantUmlLineKind::Footer(_) => Line::raw(plantuml_line.raw_str().to_string()).magenta(),
-                PlantUmlLineKind::Empty => Line::raw(text_line.to_string()).gray(),
+                PlantUmlLineKind::InComment(_) => Line::raw(text_line.to_string()).fg(color_scheme.comment),
+                PlantUmlLineKind::Include(_) => Line::raw(plantuml_line.raw_str().to_string()).fg(color_scheme.include),
+                PlantUmlLineKind::Title(_) => Line::raw(plantuml_line.raw_str().to_string()).fg(color_scheme.title),
+                PlantUmlLineKind::Header(_) => Line::raw(plantuml_line.raw_str().to_string()).fg(color_scheme.header_footer),
+                PlantUmlLineKind::Footer(_) => Line::raw(plantuml_line.raw_str().to_string()).fg(color_scheme.header_footer),
+                PlantUmlLineKind::Empty => Line::raw(text_line.to_string()).fg(color_scheme.empty),
                 PlantUmlLineKind::Others => {
-                    let mut highlighted_line = Line::raw(text_line.to_string());
+                    let mut highlighted_line = Line::raw(text_line.to_string()).fg(color_scheme.text);
 
                     if line.starts_with("!define ") {
-                        highlighted_line = highlighted_line.yellow();
+                        highlighted_line = highlighted_line.fg(color_scheme.define);
                     }
                     else if line.starts_with("legend ") || line.starts_with("end legend") {
-                        highlighted_line = highlighted_line.light_magenta();
+                        highlighted_line = highlighted_line.fg(color_scheme.legend);
                     }
                     else if line.starts_with("skinparam") {
-                        highlighted_line = highlighted_line.green();
+                        highlighted_line = highlighted_line.fg(color_scheme.skinparam);
                     }
 
                     highlighted_line
@@ -96,17 +225,17 @@ fn syntax_highlighting<'a>(input: &str) -> Vec<Line<'a>> {
             };
 
             if in_comment_block {
-                line = line.dim()
+                line = line.fg(color_scheme.comment)
             }
             
             lines.push(line);
         }
     }
     
-    return lines;
+    lines
 }
 
-#[derive(ValueEnum, Display, Default, Clone)]
+#[derive(ValueEnum, Display, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PlantUmlExtensions {
     Eps,
     Latex,
@@ -116,11 +245,113 @@ pub enum PlantUmlExtensions {
     Svg,
     Vxd,
     Txt,
-    Utxt
+    Utxt,
+    /// Every format above
+    All
 }
 
 impl PlantUmlExtensions {
-    pub fn to_output_format(&self) -> String {
+    /// Replaces `All` by every format and removes duplicates
+    pub fn expand(extensions: &[PlantUmlExtensions]) -> Vec<PlantUmlExtensions> {
+        let mut expanded = vec![];
+
+        for extension in extensions {
+            let extensions = match extension {
+                PlantUmlExtensions::All => PlantUmlExtensions::value_variants(),
+                extension => std::slice::from_ref(extension)
+            };
+
+            for extension in extensions {
+                if *extension != PlantUmlExtensions::All && !expanded.contains(extension) {
+                    expanded.push(*extension);
+                }
+            }
+        }
+
+        expanded
+    }
+
+    pub fn to_output_format(self) -> String {
         format!("-t{}", self.to_string().to_lowercase())
     }
+
+    /// Extension of the files produced by PlantUML for this format
+    pub fn to_file_extension(self) -> String {
+        match self {
+            PlantUmlExtensions::Latex => String::from("tex"),
+            PlantUmlExtensions::Txt => String::from("atxt"),
+            _ => self.to_string().to_lowercase()
+        }
+    }
+
+    /// Format producing files with the given extension, e.g. `svg` or `tex`
+    pub fn from_file_extension(file_extension: &str) -> Option<PlantUmlExtensions> {
+        let file_extension = file_extension.to_lowercase();
+
+        PlantUmlExtensions::value_variants()
+            .iter()
+            .filter(|extension| **extension != PlantUmlExtensions::All)
+            .find(|extension| extension.to_file_extension() == file_extension || extension.to_string().to_lowercase() == file_extension)
+            .copied()
+    }
+}
+
+/// A `@startXYZ` ... `@endXYZ` block of a PlantUML file
+pub struct DiagramBlock {
+    /// Identifier given after the start keyword, e.g. `@startuml sequence`
+    pub id: Option<String>,
+    /// Index of the start line in the file
+    pub start_line: usize,
+    pub source: String,
+}
+
+/// Splits a PlantUML file into its diagrams, the whole text being a single diagram when it holds no block
+pub fn split_diagram_blocks(text: &str) -> Vec<DiagramBlock> {
+    let mut blocks = vec![];
+    let mut current_block: Option<DiagramBlock> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let kind = PlantUmlLine::parse(line.into())
+            .map(|(_, (_, plantuml_line))| plantuml_line.kind().clone())
+            .unwrap_or(PlantUmlLineKind::Others);
+
+        match kind {
+            PlantUmlLineKind::Start(start_line) => {
+                current_block = Some(DiagramBlock {
+                    id: start_line.id().map(String::from),
+                    start_line: index,
+                    source: String::new(),
+                });
+            },
+            PlantUmlLineKind::End(_) => {
+                if let Some(mut block) = current_block.take() {
+                    block.source.push_str(line);
+                    block.source.push('\n');
+                    blocks.push(block);
+                }
+
+                continue;
+            },
+            _ => {}
+        }
+
+        if let Some(block) = current_block.as_mut() {
+            block.source.push_str(line);
+            block.source.push('\n');
+        }
+    }
+
+    if let Some(block) = current_block {
+        blocks.push(block);
+    }
+
+    if blocks.is_empty() {
+        blocks.push(DiagramBlock {
+            id: None,
+            start_line: 0,
+            source: text.to_string(),
+        });
+    }
+
+    blocks
 }
\ No newline at end of file
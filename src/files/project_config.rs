@@ -0,0 +1,118 @@
+use crate::files::config::{Config, CONFIG};
+use crate::logic::lint::LintConfig;
+use anyhow::anyhow;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PROJECT_CONFIG_FILE_NAME: &str = ".plantui.toml";
+
+/// PlantUML arguments a project may set, the other ones being left to the user configuration
+const PROJECT_PLANTUML_ARGS: [&str; 7] = ["-nometadata", "-noerror", "-quiet", "-verbose", "-duration", "-failfast", "-failfast2"];
+/// Prefixes of the `-Skey=value` skin parameter and `-Pkey=value` pragma arguments, also allowed in a project
+const PROJECT_PLANTUML_ARG_PREFIXES: [&str; 2] = ["-S", "-P"];
+
+#[derive(Clone)]
+pub struct ProjectConfig {
+    /// Project configuration file, if any
+    pub path: Option<PathBuf>,
+    /// Content of the project configuration file
+    pub table: toml::Table,
+    pub lint: LintConfig,
+    pub render: ProjectRenderConfig,
+    /// User configuration overridden by the project one
+    pub config: Config,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        ProjectConfig {
+            path: None,
+            table: toml::Table::new(),
+            lint: LintConfig::default(),
+            render: ProjectRenderConfig::default(),
+            config: CONFIG.clone(),
+        }
+    }
+}
+
+/// Defaults of `plantui render` for the files of the project
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ProjectRenderConfig {
+    /// Output directory, mirroring the directory structure of the project
+    pub output_dir: Option<PathBuf>,
+    /// Output file names, with the same placeholders as `--name-template`
+    pub name_template: Option<String>,
+    /// Renders in dark mode, the outputs being light otherwise whatever the mode of the TUI
+    pub dark_mode: bool,
+}
+
+/// Walks up from the input file directory until a project configuration file is found
+pub fn find_project_config(input_file_path: &Path) -> Option<PathBuf> {
+    input_file_path
+        .ancestors()
+        .skip(1)
+        .map(|directory| directory.join(PROJECT_CONFIG_FILE_NAME))
+        .find(|config_path| config_path.is_file())
+}
+
+/// PlantUML arguments only changing the rendering, which a project may set
+fn is_project_plantuml_arg(arg: &str) -> bool {
+    PROJECT_PLANTUML_ARGS.contains(&arg) || PROJECT_PLANTUML_ARG_PREFIXES.iter().any(|prefix| arg.starts_with(prefix) && arg.contains('='))
+}
+
+pub fn load_project_config(input_file_path: &Path) -> anyhow::Result<ProjectConfig> {
+    let Some(config_path) = find_project_config(input_file_path) else {
+        return Ok(ProjectConfig::default());
+    };
+
+    let content = fs::read_to_string(&config_path)?;
+    let project_dir_path = config_path.parent().unwrap();
+    let invalid = |err: &dyn std::fmt::Display| anyhow!("Invalid project configuration \"{}\"\n{}", config_path.display(), err);
+
+    let table = content.parse::<toml::Table>().map_err(|err| invalid(&err))?;
+
+    // Opening a file of a cloned repository must not run a command chosen by the repository
+    if table.get("renderer").and_then(|renderer| renderer.get("command")).is_some() {
+        return Err(invalid(&"renderer.command can only be set in the user configuration"));
+    }
+
+    // Nor start PlantUML with arguments writing files or opening servers, e.g. `-o` or `-picoweb`
+    if let Some(args) = table.get("plantuml").and_then(|plantuml| plantuml.get("args")).and_then(|args| args.as_array()) {
+        for arg in args.iter().filter_map(|arg| arg.as_str()) {
+            if !is_project_plantuml_arg(arg) {
+                return Err(invalid(&format!("plantuml.args \"{arg}\" can only be set in the user configuration")));
+            }
+        }
+    }
+
+    // The sections only found in the project configuration are left out of the user configuration overrides
+    let mut overrides = table.clone();
+
+    let lint = match overrides.remove("lint") {
+        Some(lint) => lint.try_into::<LintConfig>().map_err(|err| invalid(&err))?,
+        None => LintConfig::default()
+    };
+
+    let mut render = match overrides.remove("render") {
+        Some(render) => render.try_into::<ProjectRenderConfig>().map_err(|err| invalid(&err))?,
+        None => ProjectRenderConfig::default()
+    };
+
+    if let Some(output_dir) = render.output_dir.as_mut() {
+        *output_dir = project_dir_path.join(&output_dir);
+    }
+
+    let config = CONFIG
+        .with_overrides(&overrides, project_dir_path)
+        .map_err(|err| invalid(&format!("{err:#}")))?;
+
+    Ok(ProjectConfig {
+        path: Some(config_path.clone()),
+        table,
+        lint,
+        render,
+        config,
+    })
+}
@@ -0,0 +1,122 @@
+use chrono::{DateTime, Local};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use anyhow::anyhow;
+
+const SCRATCH_DIR_NAME: &str = "scratch";
+const SCRATCH_EXTENSION: &str = "puml";
+/// Single scratch file of the previous versions, overwritten on each launch
+const LEGACY_SCRATCH_FILE_NAME: &str = "temp";
+
+/// Diagram sketched without an input file, kept in the data directory
+#[derive(Clone)]
+pub struct Scratchpad {
+    pub name: String,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+impl Scratchpad {
+    pub fn modified_label(&self) -> String {
+        DateTime::<Local>::from(self.modified).format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+pub fn get_scratch_dir(data_dir: &Path) -> PathBuf {
+    let scratch_dir = data_dir.join(SCRATCH_DIR_NAME);
+
+    if !scratch_dir.exists() {
+        fs::create_dir_all(&scratch_dir).unwrap_or_else(|_| panic!("Could not create scratch directory \"{}\"", scratch_dir.display()));
+    }
+
+    scratch_dir
+}
+
+pub fn is_scratchpad(data_dir: &Path, path: &Path) -> bool {
+    path.parent() == Some(data_dir.join(SCRATCH_DIR_NAME).as_path())
+}
+
+/// Path of the scratchpad, the extension being appended so that e.g. `v1.2` is kept whole
+pub fn scratchpad_path(data_dir: &Path, name: &str) -> PathBuf {
+    get_scratch_dir(data_dir).join(format!("{name}.{SCRATCH_EXTENSION}"))
+}
+
+/// Path of a new scratchpad named after the current time
+pub fn new_scratchpad_path(data_dir: &Path) -> PathBuf {
+    let name = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let mut path = scratchpad_path(data_dir, &name);
+    let mut index = 1;
+
+    while path.exists() {
+        index += 1;
+        path = scratchpad_path(data_dir, &format!("{name}-{index}"));
+    }
+
+    path
+}
+
+/// Scratchpads, the most recently modified first
+pub fn list_scratchpads(data_dir: &Path) -> Vec<Scratchpad> {
+    migrate_legacy_scratchpad(data_dir);
+
+    let Ok(entries) = fs::read_dir(get_scratch_dir(data_dir)) else {
+        return vec![];
+    };
+
+    let mut scratchpads = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == SCRATCH_EXTENSION))
+        .map(|path| Scratchpad {
+            name: path.file_stem().unwrap().to_string_lossy().to_string(),
+            modified: fs::metadata(&path).and_then(|metadata| metadata.modified()).unwrap_or(SystemTime::UNIX_EPOCH),
+            path,
+        })
+        .collect::<Vec<_>>();
+
+    scratchpads.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.name.cmp(&b.name)));
+    scratchpads
+}
+
+/// Most recent scratchpad, reopened when no input file is given
+pub fn latest_scratchpad_path(data_dir: &Path) -> PathBuf {
+    match list_scratchpads(data_dir).into_iter().next() {
+        Some(scratchpad) => scratchpad.path,
+        None => new_scratchpad_path(data_dir)
+    }
+}
+
+pub fn delete_scratchpad(path: &Path) -> anyhow::Result<()> {
+    fs::remove_file(path).map_err(|err| anyhow!("Could not delete scratchpad \"{}\": {}", path.display(), err))
+}
+
+/// Keeps the diagram of the single scratch file instead of losing it
+fn migrate_legacy_scratchpad(data_dir: &Path) {
+    let legacy_path = data_dir.join(LEGACY_SCRATCH_FILE_NAME);
+
+    if legacy_path.is_file() {
+        let path = scratchpad_path(data_dir, LEGACY_SCRATCH_FILE_NAME);
+
+        if !path.exists() {
+            fs::rename(&legacy_path, &path).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn keeps_the_dots_of_a_scratchpad_name() {
+        let data_dir = env::temp_dir().join(format!("plantui-scratchpad-test-{}", std::process::id()));
+        let path = scratchpad_path(&data_dir, "v1.2");
+
+        assert_eq!(path.file_name().unwrap(), "v1.2.puml");
+        assert!(is_scratchpad(&data_dir, &path));
+
+        fs::remove_dir_all(&data_dir).ok();
+    }
+}
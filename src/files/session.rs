@@ -0,0 +1,125 @@
+use crate::args::ARGS;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+
+const SESSIONS_FILE_NAME: &str = "sessions.json";
+
+/// Editor state remembered for a file between runs
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct FileSession {
+    /// PlantUML theme chosen in the preview
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// Line and column of the cursor, the editor scrolling to it
+    pub cursor_position: (u16, u16),
+    /// First line and column shown in the editor
+    pub scroll_offset: (u16, u16),
+    /// Width of the editor, in percent of the terminal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dark_mode: Option<bool>,
+    /// Zoom of the preview, in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zoom: Option<u16>,
+    /// Outcome of the last render, shown in the sidebar
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub render_status: Option<RenderStatus>,
+    /// Diagram edited in a Markdown or AsciiDoc document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedded_diagram: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderStatus {
+    Rendered,
+    Failed,
+}
+
+fn read_sessions(data_dir: &Path) -> HashMap<String, FileSession> {
+    fs::read_to_string(data_dir.join(SESSIONS_FILE_NAME))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Sessions of every file, by path
+pub fn load_file_sessions(data_dir: &Path) -> HashMap<PathBuf, FileSession> {
+    read_sessions(data_dir)
+        .into_iter()
+        .map(|(input_file_path, file_session)| (PathBuf::from(input_file_path), file_session))
+        .collect()
+}
+
+pub fn load_file_session(data_dir: &Path, input_file_path: &Path) -> FileSession {
+    read_sessions(data_dir)
+        .remove(&input_file_path.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+pub fn save_file_session(data_dir: &Path, input_file_path: &Path, file_session: &FileSession) -> anyhow::Result<()> {
+    if ARGS.no_session {
+        return Ok(());
+    }
+
+    let mut sessions = read_sessions(data_dir);
+    sessions.insert(input_file_path.to_string_lossy().to_string(), file_session.clone());
+
+    write_sessions(data_dir, &sessions)
+}
+
+/// Drops the session of a deleted file
+pub fn forget_file_session(data_dir: &Path, input_file_path: &Path) -> anyhow::Result<()> {
+    if ARGS.no_session {
+        return Ok(());
+    }
+
+    let mut sessions = read_sessions(data_dir);
+
+    if sessions.remove(&input_file_path.to_string_lossy().to_string()).is_none() {
+        return Ok(());
+    }
+
+    write_sessions(data_dir, &sessions)
+}
+
+fn write_sessions(data_dir: &Path, sessions: &HashMap<String, FileSession>) -> anyhow::Result<()> {
+    let sessions_path = data_dir.join(SESSIONS_FILE_NAME);
+
+    fs::write(&sessions_path, serde_json::to_string_pretty(sessions)?)
+        .map_err(|err| anyhow!("Could not write sessions in \"{}\": {}", sessions_path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_the_scroll_and_the_zoom() {
+        let file_session = FileSession {
+            cursor_position: (42, 3),
+            scroll_offset: (30, 0),
+            zoom: Some(150),
+            ..FileSession::default()
+        };
+
+        let restored: FileSession = serde_json::from_str(&serde_json::to_string(&file_session).unwrap()).unwrap();
+
+        assert_eq!(restored.cursor_position, (42, 3));
+        assert_eq!(restored.scroll_offset, (30, 0));
+        assert_eq!(restored.zoom, Some(150));
+    }
+
+    #[test]
+    fn reads_the_sessions_of_previous_versions() {
+        let restored: FileSession = serde_json::from_str(r#"{"cursor_position": [4, 2], "split": 60}"#).unwrap();
+
+        assert_eq!(restored.scroll_offset, (0, 0));
+        assert_eq!(restored.zoom, None);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::files::atomic_write::write_atomically;
+use crate::files::utils::fnv1a_hash;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::{fs, io};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SWAP_DIR_NAME: &str = "swap";
+/// Unchanged lines shown around the changes of a diff
+const DIFF_CONTEXT_LINES: usize = 2;
+
+/// Unsaved text of a file, written periodically to be recovered after a crash
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SwapFile {
+    pub input_file_path: PathBuf,
+    /// Process which wrote the swap file, which is orphaned once it is gone
+    pub pid: u32,
+    /// Unix time in seconds
+    pub written_at: u64,
+    pub text: String,
+}
+
+impl SwapFile {
+    pub fn written_at_label(&self) -> String {
+        let written_at = UNIX_EPOCH + std::time::Duration::from_secs(self.written_at);
+        DateTime::<Local>::from(written_at).format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DiffLineKind {
+    Added,
+    Removed,
+    Unchanged,
+    /// Unchanged lines left out
+    Skipped,
+}
+
+#[derive(Clone)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub text: String,
+}
+
+/// Swap files are named after a hash of the absolute path, which is too long to be a file name
+fn swap_file_path(data_dir: &Path, input_file_path: &Path) -> PathBuf {
+    let file_name = input_file_path.file_name().unwrap_or_default().to_string_lossy();
+
+    data_dir
+        .join(SWAP_DIR_NAME)
+        .join(format!("{}-{:016x}.swp", file_name, fnv1a_hash(input_file_path.as_os_str().as_encoded_bytes())))
+}
+
+pub fn write_swap_file(data_dir: &Path, input_file_path: &Path, text: &str) -> anyhow::Result<()> {
+    let swap_file_path = swap_file_path(data_dir, input_file_path);
+    fs::create_dir_all(swap_file_path.parent().unwrap())?;
+
+    let swap_file = SwapFile {
+        input_file_path: input_file_path.to_path_buf(),
+        pid: process::id(),
+        written_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0),
+        text: text.to_string(),
+    };
+
+    write_atomically(&swap_file_path, serde_json::to_string(&swap_file)?.as_bytes())
+}
+
+pub fn remove_swap_file(data_dir: &Path, input_file_path: &Path) {
+    fs::remove_file(swap_file_path(data_dir, input_file_path)).ok();
+}
+
+/// Swap file left by a process which is gone, holding a text different from the saved one
+pub fn find_orphaned_swap_file(data_dir: &Path, input_file_path: &Path, saved_text: &str) -> Option<SwapFile> {
+    let content = fs::read_to_string(swap_file_path(data_dir, input_file_path)).ok()?;
+    let swap_file = serde_json::from_str::<SwapFile>(&content).ok()?;
+
+    if swap_file.input_file_path != input_file_path || swap_file.pid == process::id() || is_process_running(swap_file.pid) {
+        return None;
+    }
+
+    if swap_file.text == saved_text {
+        remove_swap_file(data_dir, input_file_path);
+        return None;
+    }
+
+    Some(swap_file)
+}
+
+/// Another PlanTUI may still be editing the file, a process owned by another user still existing
+#[cfg(unix)]
+fn is_process_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+
+    // Signal 0 only checks that the process exists
+    match unsafe { libc::kill(pid, 0) } {
+        0 => true,
+        _ => io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+}
+
+/// Without a way to tell, the process is assumed alive so that its swap file is left alone
+#[cfg(not(unix))]
+fn is_process_running(_pid: u32) -> bool {
+    true
+}
+
+/// Changed lines from the old text to the new one, with a few unchanged lines around them
+pub fn diff_lines(old_text: &str, new_text: &str) -> Vec<DiffLine> {
+    let text_diff = TextDiff::from_lines(old_text, new_text);
+    let mut diff_lines = vec![];
+
+    for (index, group) in text_diff.grouped_ops(DIFF_CONTEXT_LINES).iter().enumerate() {
+        if index > 0 {
+            diff_lines.push(DiffLine {
+                kind: DiffLineKind::Skipped,
+                text: String::from("…"),
+            });
+        }
+
+        for op in group {
+            for change in text_diff.iter_changes(op) {
+                let kind = match change.tag() {
+                    ChangeTag::Insert => DiffLineKind::Added,
+                    ChangeTag::Delete => DiffLineKind::Removed,
+                    ChangeTag::Equal => DiffLineKind::Unchanged
+                };
+
+                diff_lines.push(DiffLine {
+                    kind,
+                    text: change.value().trim_end_matches(['\r', '\n']).to_string(),
+                });
+            }
+        }
+    }
+
+    diff_lines
+}
@@ -1,9 +1,9 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use directories::UserDirs;
 
-pub fn expand_tilde(path_buf: &PathBuf) -> PathBuf {
+pub fn expand_tilde(path_buf: &Path) -> PathBuf {
     if !path_buf.starts_with("~/") {
-        return path_buf.clone();
+        return path_buf.to_path_buf();
     }
 
     match UserDirs::new() {
@@ -14,4 +14,15 @@ pub fn expand_tilde(path_buf: &PathBuf) -> PathBuf {
         },
         None => panic!("No home directory found when trying to expand \"~\"")
     }
-}
\ No newline at end of file
+}
+/// FNV-1a hash, stable across builds and platforms unlike the standard library's hasher
+pub fn fnv1a_hash<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
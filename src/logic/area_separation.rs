@@ -1,15 +1,93 @@
-use crate::app::App;
+use crate::app::{App, Pane};
+use crokey::crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+
+/// The editor and the preview keep at least this share of the main area, unless maximized
+const MIN_AREA_PERCENTAGE: u16 = 10;
+/// Step of the fine resizing, in percent
+const FINE_RESIZE_STEP: u16 = 1;
 
 impl App<'_> {
-    pub fn shrink_left_area(&mut self) {
-        if self.left_area_percentage > 0 {
-            self.left_area_percentage -= 10;
-        }
+    pub fn shrink_editor(&mut self) {
+        self.shrink_editor_by(self.buffer.project_config.config.ui.resize_step);
+    }
+
+    pub fn expand_editor(&mut self) {
+        self.expand_editor_by(self.buffer.project_config.config.ui.resize_step);
+    }
+
+    pub fn shrink_editor_finely(&mut self) {
+        self.shrink_editor_by(FINE_RESIZE_STEP);
+    }
+
+    pub fn expand_editor_finely(&mut self) {
+        self.expand_editor_by(FINE_RESIZE_STEP);
+    }
+
+    /// Resizing shows both panes again
+    pub fn set_editor_percentage(&mut self, editor_percentage: u16) {
+        self.editor_percentage = clamp_editor_percentage(editor_percentage);
+        self.maximized_pane = None;
+    }
+
+    pub fn toggle_layout(&mut self) {
+        self.vertical_layout = !self.vertical_layout;
+    }
+
+    /// Gives the whole main area to the pane, or shows both panes when it already has it
+    pub fn toggle_maximized_pane(&mut self, pane: Pane) {
+        self.maximized_pane = match self.maximized_pane == Some(pane) {
+            true => None,
+            false => Some(pane)
+        };
     }
 
-    pub fn expand_left_area(&mut self) {
-        if self.left_area_percentage < 100 {
-            self.left_area_percentage += 10;
+    /// Drags the divider between the editor and the preview
+    pub fn handle_mouse_event(&mut self, mouse_event: MouseEvent) {
+        if !self.buffer.project_config.config.ui.mouse || self.maximized_pane.is_some() {
+            return;
+        }
+
+        let area = self.split_area;
+        let (position, start, length) = match self.vertical_layout {
+            true => (mouse_event.row, area.y, area.height),
+            false => (mouse_event.column, area.x, area.width)
+        };
+
+        if length == 0 {
+            return;
         }
+
+        // The divider is the border of the preview, a click next to it grabbing it too
+        let divider_position = start + (length as u32 * self.editor_percentage as u32 / 100) as u16;
+
+        match mouse_event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let (cross_position, cross_start, cross_length) = match self.vertical_layout {
+                    true => (mouse_event.column, area.x, area.width),
+                    false => (mouse_event.row, area.y, area.height)
+                };
+
+                self.dragging_split = position.abs_diff(divider_position) <= 1
+                    && (cross_start..cross_start + cross_length).contains(&cross_position);
+            },
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging_split => {
+                let offset = position.saturating_sub(start).min(length) as u32;
+                self.set_editor_percentage((offset * 100 / length as u32) as u16);
+            },
+            MouseEventKind::Up(MouseButton::Left) => self.dragging_split = false,
+            _ => {}
+        }
+    }
+
+    fn shrink_editor_by(&mut self, step: u16) {
+        self.set_editor_percentage(self.editor_percentage.saturating_sub(step));
     }
-}
\ No newline at end of file
+
+    fn expand_editor_by(&mut self, step: u16) {
+        self.set_editor_percentage(self.editor_percentage + step);
+    }
+}
+
+pub fn clamp_editor_percentage(editor_percentage: u16) -> u16 {
+    editor_percentage.clamp(MIN_AREA_PERCENTAGE, 100 - MIN_AREA_PERCENTAGE)
+}
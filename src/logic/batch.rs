@@ -0,0 +1,461 @@
+use crate::error::CliError;
+use crate::files::embedded::{document_kind, embedded_diagram_blocks};
+use crate::files::include::included_file_paths;
+use crate::files::pmu::{split_diagram_blocks, DiagramBlock, PlantUmlExtensions};
+use crate::files::project_config::load_project_config;
+use crate::files::utils::{expand_tilde, fnv1a_hash};
+use crate::render::{effective_plantuml_mode, effective_plantuml_options, parse_plantuml_error, render_command, render_pipe_command, PlantUmlOptions};
+use anyhow::{anyhow, bail};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use serde::Serialize;
+use std::{fs, io, iter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+const GLOB_CHARS: [char; 4] = ['*', '?', '[', '{'];
+const HASH_MANIFEST_FILE_NAME: &str = ".plantui-hashes";
+
+/// Name template of the outputs rendered without an explicit output file name
+pub const DEFAULT_NAME_TEMPLATE: &str = "{stem}.{ext}";
+
+#[derive(ValueEnum, Default, Clone, Copy, PartialEq)]
+pub enum SkipStrategy {
+    /// Skip diagrams whose output is more recent than their source and its included files
+    #[default]
+    Mtime,
+    /// Skip diagrams whose source and included files did not change since the last render
+    Hash,
+    /// Always render
+    Never,
+}
+
+#[derive(Clone)]
+pub struct RenderJob {
+    pub input_file_path: PathBuf,
+    pub output_dir_path: PathBuf,
+    /// Output file names relative to the output directory, PlantUML naming the outputs when missing
+    pub name_template: Option<String>,
+    /// Options of the user configuration, the project holding the input file and the command line
+    pub plantuml_options: PlantUmlOptions,
+    /// `-darkmode` or nothing, from the command line or the configuration
+    pub mode: &'static str,
+}
+
+pub enum RenderJobStatus {
+    Rendered,
+    UpToDate,
+    Failed(RenderFailure),
+}
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum RenderFailure {
+    MissingRenderer { message: String },
+    Syntax { line: Option<usize>, message: String },
+    Io { message: String },
+}
+
+impl RenderFailure {
+    pub fn message(&self) -> &str {
+        match self {
+            RenderFailure::MissingRenderer { message } | RenderFailure::Syntax { message, .. } | RenderFailure::Io { message } => message
+        }
+    }
+}
+
+/// Expands files and glob patterns into render jobs, mirroring the directory structure under the output directory
+pub fn collect_render_jobs(inputs: &[String], output_dir_path: Option<&Path>, name_template: Option<&str>) -> anyhow::Result<Vec<RenderJob>> {
+    let mut jobs: Vec<RenderJob> = vec![];
+
+    for input in inputs {
+        let input = expand_tilde(Path::new(input));
+        let input_str = input.to_string_lossy();
+
+        let (base_dir_path, input_file_paths) = match input_str.contains(GLOB_CHARS) {
+            true => {
+                let mut input_file_paths = vec![];
+
+                let entries = glob::glob(&input_str).map_err(|err| CliError::Usage(format!("Invalid pattern \"{}\": {}", input_str, err)))?;
+
+                for entry in entries {
+                    let path = entry?;
+
+                    if path.is_file() {
+                        input_file_paths.push(path);
+                    }
+                }
+
+                if input_file_paths.is_empty() {
+                    bail!(CliError::Usage(format!("Pattern \"{}\" does not match any file", input_str)));
+                }
+
+                (glob_base(&input), input_file_paths)
+            },
+            false => {
+                if !input.is_file() {
+                    bail!(CliError::Usage(format!("Input \"{}\" does not exist or is not a file", input.display())));
+                }
+
+                (input.parent().map(Path::to_path_buf).unwrap_or_default(), vec![input.clone()])
+            }
+        };
+
+        for input_file_path in input_file_paths {
+            let input_file_path = std::path::absolute(&input_file_path)?;
+            // A file name without directory is relative to the current one
+            let base_dir_path = match base_dir_path.as_os_str().is_empty() {
+                true => std::env::current_dir()?,
+                false => std::path::absolute(&base_dir_path)?
+            };
+            let input_dir_path = input_file_path.parent().unwrap().to_path_buf();
+
+            if jobs.iter().any(|job| job.input_file_path == input_file_path) {
+                continue;
+            }
+
+            let project_config = load_project_config(&input_file_path)?;
+
+            let output_dir_path = match (output_dir_path, &project_config.render.output_dir, &project_config.path) {
+                (Some(output_dir_path), _, _) => {
+                    let relative_dir_path = input_dir_path.strip_prefix(&base_dir_path).unwrap_or(Path::new(""));
+                    output_dir_path.join(relative_dir_path)
+                },
+                // The project output directory mirrors the project structure, whatever the inputs given
+                (None, Some(project_output_dir_path), Some(project_config_path)) => {
+                    let relative_dir_path = input_dir_path.strip_prefix(project_config_path.parent().unwrap()).unwrap_or(Path::new(""));
+                    project_output_dir_path.join(relative_dir_path)
+                },
+                (None, _, _) => input_dir_path
+            };
+
+            // The diagrams of a document are rendered one by one
+            let name_template = name_template
+                .map(String::from)
+                .or(project_config.render.name_template.clone())
+                .or_else(|| document_kind(&input_file_path).map(|_| String::from(DEFAULT_NAME_TEMPLATE)));
+
+            jobs.push(RenderJob {
+                input_file_path,
+                output_dir_path,
+                name_template,
+                plantuml_options: effective_plantuml_options(&project_config),
+                mode: effective_plantuml_mode(&project_config),
+            });
+        }
+    }
+
+    Ok(jobs)
+}
+
+/// Renders all the jobs in every extension with at most `max_jobs` PlantUML processes at the same time
+pub async fn render_jobs(jobs: Vec<RenderJob>, extensions: &[PlantUmlExtensions], max_jobs: usize, skip_strategy: SkipStrategy) -> anyhow::Result<Vec<(RenderJob, PlantUmlExtensions, RenderJobStatus)>> {
+    let mut hash_manifests = HashMap::new();
+
+    if skip_strategy == SkipStrategy::Hash {
+        for job in &jobs {
+            if !hash_manifests.contains_key(&job.output_dir_path) {
+                hash_manifests.insert(job.output_dir_path.clone(), read_hash_manifest(&job.output_dir_path));
+            }
+        }
+    }
+
+    let hash_manifests = Arc::new(hash_manifests);
+    let semaphore = Arc::new(Semaphore::new(max_jobs.max(1)));
+    let mut join_set = JoinSet::new();
+
+    for job in jobs {
+        for extension in extensions {
+            let job = job.clone();
+            let extension = *extension;
+            let semaphore = semaphore.clone();
+            let hash_manifests = hash_manifests.clone();
+
+            join_set.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+
+                let output_format = extension.to_output_format();
+                let output_file_paths = output_file_paths(&job, extension);
+
+                let source_file_paths = match skip_strategy {
+                    SkipStrategy::Never => vec![],
+                    _ => source_file_paths(&job.input_file_path)
+                };
+
+                let source_hash = match skip_strategy {
+                    SkipStrategy::Hash => Some(hash_source(&source_file_paths, &output_format, job.mode, &job.plantuml_options)),
+                    _ => None
+                };
+
+                let up_to_date = match skip_strategy {
+                    SkipStrategy::Mtime => output_file_paths
+                        .iter()
+                        .all(|output_file_path| is_output_more_recent(&source_file_paths, output_file_path)),
+                    SkipStrategy::Hash => {
+                        let output_file_name = hash_manifest_key(&output_file_paths);
+
+                        hash_manifests
+                            .get(&job.output_dir_path)
+                            .and_then(|hash_manifest| hash_manifest.get(&output_file_name))
+                            .is_some_and(|hash| Some(hash) == source_hash.as_ref()) && output_file_paths.iter().all(|output_file_path| output_file_path.exists())
+                    },
+                    SkipStrategy::Never => false
+                };
+
+                let status = match up_to_date {
+                    true => RenderJobStatus::UpToDate,
+                    false => render_job(&job, extension).await
+                };
+
+                (job, extension, status, source_hash)
+            });
+        }
+    }
+
+    let mut joined_jobs = vec![];
+
+    while let Some(joined) = join_set.join_next().await {
+        joined_jobs.push(joined?);
+    }
+
+    // Every task is finished, so the manifests are not shared anymore
+    let mut hash_manifests = Arc::try_unwrap(hash_manifests).unwrap_or_default();
+    let mut results = vec![];
+
+    for (job, extension, status, source_hash) in joined_jobs {
+        if let (RenderJobStatus::Rendered, Some(source_hash)) = (&status, source_hash) {
+            let output_file_name = hash_manifest_key(&output_file_paths(&job, extension));
+
+            hash_manifests
+                .entry(job.output_dir_path.clone())
+                .or_default()
+                .insert(output_file_name, source_hash);
+        }
+
+        results.push((job, extension, status));
+    }
+
+    for (output_dir_path, hash_manifest) in hash_manifests.iter().filter(|(output_dir_path, _)| output_dir_path.is_dir()) {
+        write_hash_manifest(output_dir_path, hash_manifest)?;
+    }
+
+    results.sort_by_key(|(job, extension, _)| (job.input_file_path.clone(), extensions.iter().position(|other| other == extension)));
+
+    Ok(results)
+}
+
+async fn render_job(job: &RenderJob, extension: PlantUmlExtensions) -> RenderJobStatus {
+    if let Some(name_template) = &job.name_template {
+        let content = match fs::read_to_string(&job.input_file_path) {
+            Ok(content) => content,
+            Err(err) => return RenderJobStatus::Failed(RenderFailure::Io { message: err.to_string() })
+        };
+
+        let stem = job.input_file_path.file_stem().unwrap().to_string_lossy();
+        let working_dir_path = job.input_file_path.parent().unwrap();
+
+        return match render_diagram_blocks(&diagram_blocks(&job.input_file_path, &content), &stem, name_template, &job.output_dir_path, working_dir_path, extension, job.mode, &job.plantuml_options).await {
+            Ok(_) => RenderJobStatus::Rendered,
+            Err(failure) => RenderJobStatus::Failed(failure)
+        };
+    }
+
+    if let Err(err) = fs::create_dir_all(&job.output_dir_path) {
+        return RenderJobStatus::Failed(RenderFailure::Io { message: err.to_string() });
+    }
+
+    match render_command(&extension.to_output_format(), job.mode, &job.plantuml_options, &job.output_dir_path, &job.input_file_path).await {
+        Ok(output) if output.status.success() => RenderJobStatus::Rendered,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            let failure = match stderr.contains("Error line") {
+                true => {
+                    let (line, message) = parse_plantuml_error(&stderr);
+                    RenderFailure::Syntax { line, message }
+                },
+                false => RenderFailure::Io { message: stderr.trim().to_string() }
+            };
+
+            RenderJobStatus::Failed(failure)
+        },
+        Err(err) => RenderJobStatus::Failed(command_failure(err))
+    }
+}
+
+/// Diagrams of a file: its `@startXYZ` blocks, or the PlantUML blocks of a Markdown or AsciiDoc document
+pub fn diagram_blocks(input_file_path: &Path, content: &str) -> Vec<DiagramBlock> {
+    match document_kind(input_file_path) {
+        Some(kind) => embedded_diagram_blocks(kind, content),
+        None => split_diagram_blocks(content)
+    }
+}
+
+/// Renders each diagram on its own through PlantUML's pipe mode, naming the outputs after the template
+#[allow(clippy::too_many_arguments)]
+pub async fn render_diagram_blocks(blocks: &[DiagramBlock], stem: &str, name_template: &str, output_dir_path: &Path, working_dir_path: &Path, extension: PlantUmlExtensions, mode: &str, plantuml_options: &PlantUmlOptions) -> Result<Vec<PathBuf>, RenderFailure> {
+    let output_file_paths = templated_output_file_paths(name_template, stem, blocks, output_dir_path, extension);
+
+    for (block, output_file_path) in blocks.iter().zip(&output_file_paths) {
+        let output = render_pipe_command(&extension.to_output_format(), mode, plantuml_options, block.source.as_bytes(), working_dir_path)
+            .await
+            .map_err(command_failure)?;
+
+        if !output.status.success() {
+            let (line, message) = parse_plantuml_error(&String::from_utf8_lossy(&output.stderr));
+
+            return Err(RenderFailure::Syntax {
+                line: line.map(|line| line + block.start_line),
+                message
+            });
+        }
+
+        output_file_path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(output_file_path, &output.stdout))
+            .map_err(|err| RenderFailure::Io { message: format!("Could not write \"{}\": {}", output_file_path.display(), err) })?;
+    }
+
+    Ok(output_file_paths)
+}
+
+/// Output files of a job in the given extension
+fn output_file_paths(job: &RenderJob, extension: PlantUmlExtensions) -> Vec<PathBuf> {
+    let stem = job.input_file_path.file_stem().unwrap().to_string_lossy();
+
+    match &job.name_template {
+        None => vec![
+            job.output_dir_path
+                .join(stem.as_ref())
+                .with_extension(extension.to_file_extension())
+        ],
+        Some(name_template) => {
+            let content = fs::read_to_string(&job.input_file_path).unwrap_or_default();
+            templated_output_file_paths(name_template, &stem, &diagram_blocks(&job.input_file_path, &content), &job.output_dir_path, extension)
+        }
+    }
+}
+
+/// Output files of each diagram, indexing the names when the template gives the same one to several diagrams
+fn templated_output_file_paths(name_template: &str, stem: &str, blocks: &[DiagramBlock], output_dir_path: &Path, extension: PlantUmlExtensions) -> Vec<PathBuf> {
+    let mut file_names = apply_name_template(name_template, stem, blocks, extension);
+    let has_duplicates = file_names.iter().enumerate().any(|(index, file_name)| file_names[..index].contains(file_name));
+
+    if has_duplicates {
+        file_names = apply_name_template(&indexed_name_template(name_template), stem, blocks, extension);
+    }
+
+    file_names
+        .iter()
+        .map(|file_name| output_dir_path.join(file_name))
+        .collect()
+}
+
+/// Replaces the `{stem}`, `{name}`, `{index}` and `{ext}` placeholders for each diagram
+fn apply_name_template(name_template: &str, stem: &str, blocks: &[DiagramBlock], extension: PlantUmlExtensions) -> Vec<String> {
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| {
+            name_template
+                .replace("{stem}", stem)
+                .replace("{name}", block.id.as_deref().unwrap_or(stem))
+                .replace("{index}", &(index + 1).to_string())
+                .replace("{ext}", &extension.to_file_extension())
+        })
+        .collect()
+}
+
+/// Inserts the diagram index before the extension, e.g. `{name}.{ext}` becomes `{name}-{index}.{ext}`
+fn indexed_name_template(name_template: &str) -> String {
+    match name_template.rfind('.') {
+        Some(dot_index) if dot_index > 0 => format!("{}-{{index}}{}", &name_template[..dot_index], &name_template[dot_index..]),
+        _ => format!("{name_template}-{{index}}")
+    }
+}
+
+fn command_failure(err: anyhow::Error) -> RenderFailure {
+    match err.downcast_ref::<io::Error>() {
+        Some(io_error) if io_error.kind() == io::ErrorKind::NotFound => RenderFailure::MissingRenderer {
+            message: CliError::missing_renderer().to_string()
+        },
+        _ => RenderFailure::Io { message: err.to_string() }
+    }
+}
+
+/// Key of a job output in the hash manifest, its first output file name
+fn hash_manifest_key(output_file_paths: &[PathBuf]) -> String {
+    output_file_paths
+        .first()
+        .and_then(|output_file_path| output_file_path.file_name())
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn is_output_more_recent(source_file_paths: &[PathBuf], output_file_path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+
+    match modified(output_file_path) {
+        Some(output_modified) => source_file_paths
+            .iter()
+            .all(|source_file_path| modified(source_file_path).is_some_and(|source_modified| output_modified >= source_modified)),
+        None => false
+    }
+}
+
+/// The input file followed by the files it includes, whose changes also change the output
+fn source_file_paths(input_file_path: &Path) -> Vec<PathBuf> {
+    let text = fs::read_to_string(input_file_path).unwrap_or_default();
+
+    iter::once(input_file_path.to_path_buf())
+        .chain(included_file_paths(input_file_path, &text))
+        .collect()
+}
+
+/// Non-wildcard directory prefix of a glob pattern, e.g. `docs/` for `docs/**/*.puml`
+fn glob_base(pattern: &Path) -> PathBuf {
+    pattern
+        .components()
+        .take_while(|component| !component.as_os_str().to_string_lossy().contains(GLOB_CHARS))
+        .collect()
+}
+
+/// FNV-1a hash of the sources (the input and its included files) and of the options influencing the output
+fn hash_source(source_file_paths: &[PathBuf], output_format: &str, mode: &str, plantuml_options: &PlantUmlOptions) -> String {
+    let mut content = vec![];
+
+    for source_file_path in source_file_paths {
+        content.extend(source_file_path.as_os_str().as_encoded_bytes());
+        content.extend(fs::read(source_file_path).unwrap_or_default());
+    }
+
+    let plantuml_args = plantuml_options.to_args().join(" ");
+    let hash = fnv1a_hash(content.iter().chain(output_format.as_bytes()).chain(mode.as_bytes()).chain(plantuml_args.as_bytes()));
+
+    format!("{hash:016x}")
+}
+
+fn read_hash_manifest(output_dir_path: &Path) -> HashMap<String, String> {
+    let content = fs::read_to_string(output_dir_path.join(HASH_MANIFEST_FILE_NAME)).unwrap_or_default();
+
+    content
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(hash, file_name)| (file_name.to_string(), hash.to_string()))
+        .collect()
+}
+
+fn write_hash_manifest(output_dir_path: &Path, hash_manifest: &HashMap<String, String>) -> anyhow::Result<()> {
+    let mut lines = hash_manifest
+        .iter()
+        .map(|(file_name, hash)| format!("{hash} {file_name}"))
+        .collect::<Vec<_>>();
+
+    lines.sort();
+
+    fs::write(output_dir_path.join(HASH_MANIFEST_FILE_NAME), lines.join("\n"))
+        .map_err(|err| anyhow!("Could not write hash manifest in \"{}\": {}", output_dir_path.display(), err))
+}
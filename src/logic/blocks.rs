@@ -0,0 +1,279 @@
+use plantuml_parser::{PlantUmlLine, PlantUmlLineKind};
+
+/// Statements ending or leaving the flow of an activity diagram, which are not block keywords there
+const ACTIVITY_STATEMENTS: [&str; 6] = ["start", "stop", "end", "kill", "detach", "break"];
+
+/// Blocks holding free text, with the keyword closing them (spaces removed)
+const TEXT_BLOCKS: [(&str, &[&str]); 8] = [
+    ("note", &["endnote"]), ("rnote", &["endrnote"]), ("hnote", &["endhnote"]), ("ref", &["endref"]),
+    ("legend", &["endlegend"]), ("title", &["endtitle"]), ("header", &["endheader"]), ("footer", &["endfooter"]),
+];
+
+/// Keywords closing a block, spaces removed so that e.g. `end note` and `endnote` are the same
+const CLOSING_KEYWORDS: [&str; 18] = [
+    "end", "endif", "endwhile", "endfork", "endmerge", "endsplit", "endswitch", "endgroup", "endpartition", "endbox",
+    "endnote", "endrnote", "endhnote", "endref", "endlegend", "endtitle", "endheader", "endfooter",
+];
+
+/// Block syntax of a diagram, told by its `@start` keyword and its statements
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagramSyntax {
+    /// `@startuml` diagram made of actions, e.g. `:action;` or `if (condition) then`
+    Activity,
+    /// Other `@startuml` diagrams (sequence, class, component...), grouping with `alt`/`end` and braces
+    Uml,
+    /// Diagrams with their own syntax (e.g. `@startjson`, `@startmindmap`), only grouping with braces
+    Other,
+}
+
+/// How a line of a diagram affects the opened blocks
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockLine {
+    /// Opens a block closed by one of the keywords (spaces removed), e.g. `endif` for `if (x) then`
+    Open(&'static [&'static str]),
+    /// Opens a block of free text closed by one of the keywords, e.g. `endnote`
+    OpenText(&'static [&'static str]),
+    /// Closes the last block with the keyword (spaces removed), e.g. `endif`, `endfork` or `}`
+    Close(String),
+    /// Starts the next section of the last block, e.g. `else` or `fork again`
+    Reopen,
+    None,
+}
+
+/// Line of a PlantUML text, as seen by both the formatter and the linter
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagramLine {
+    /// `@start` line, with the diagram kind (e.g. `uml`) and the syntax of the diagram
+    Start(String, DiagramSyntax),
+    End,
+    /// Line opening a block comment
+    CommentOpen,
+    /// Line inside a block comment or closing it, which must be kept as it is
+    Comment,
+    /// `!include` line, with the included path
+    Include(String),
+    /// Blank or comment-only line
+    Empty,
+    /// Free text of a note, a legend or a title, including single line titles, headers and footers
+    Text,
+    /// Any other line, with its effect on the opened blocks
+    Statement(BlockLine),
+}
+
+/// Classifies each line of the text, telling the syntax of each diagram from its statements
+pub fn classify_lines(lines: &[&str]) -> Vec<DiagramLine> {
+    let mut in_comment_block = false;
+
+    // Statements are only classified once the syntax of their diagram is known
+    let mut classified = lines
+        .iter()
+        .map(|line| {
+            let trimmed = line.trim();
+
+            let kind = PlantUmlLine::parse((*line).into())
+                .map(|(_, (_, plantuml_line))| plantuml_line.kind().clone())
+                .unwrap_or(PlantUmlLineKind::Others);
+
+            match kind {
+                PlantUmlLineKind::BlockCommentOpen(_) => {
+                    in_comment_block = !trimmed.contains("'/");
+                    Some(DiagramLine::CommentOpen)
+                },
+                PlantUmlLineKind::BlockCommentClose(_) => {
+                    in_comment_block = false;
+                    Some(DiagramLine::Comment)
+                },
+                _ if in_comment_block => Some(DiagramLine::Comment),
+                PlantUmlLineKind::Start(start) => Some(DiagramLine::Start(start.diagram_kind().to_string(), DiagramSyntax::Uml)),
+                PlantUmlLineKind::End(_) => Some(DiagramLine::End),
+                PlantUmlLineKind::Include(include_line) => Some(DiagramLine::Include(include_line.token().filepath().to_string())),
+                PlantUmlLineKind::Empty | PlantUmlLineKind::InComment(_) => Some(DiagramLine::Empty),
+                PlantUmlLineKind::Title(_) | PlantUmlLineKind::Header(_) | PlantUmlLineKind::Footer(_) => match block_line(trimmed, DiagramSyntax::Uml) {
+                    BlockLine::None => Some(DiagramLine::Text),
+                    _ => None
+                },
+                _ => None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Lines before the first `@start` line, e.g. in a document, are read as a `@startuml` diagram
+    let leading_statements = lines
+        .iter()
+        .zip(&classified)
+        .take_while(|(_, line)| !matches!(line, Some(DiagramLine::Start(_, _))))
+        .filter(|(_, line)| line.is_none())
+        .map(|(line, _)| line.trim());
+    let mut syntax = diagram_syntax("uml", leading_statements);
+    let mut text_block: Option<&'static [&'static str]> = None;
+
+    for index in 0..lines.len() {
+        match classified[index].clone() {
+            // A diagram goes on up to the next one, even when it is not closed
+            Some(DiagramLine::Start(kind, _)) => {
+                let next_start = classified[index + 1..]
+                    .iter()
+                    .position(|line| matches!(line, Some(DiagramLine::Start(_, _))))
+                    .map(|position| index + 1 + position)
+                    .unwrap_or(lines.len());
+
+                syntax = diagram_syntax(&kind, (index + 1..next_start).filter(|&index| classified[index].is_none()).map(|index| lines[index].trim()));
+                classified[index] = Some(DiagramLine::Start(kind, syntax));
+                text_block = None;
+            },
+            Some(DiagramLine::End) => text_block = None,
+            Some(_) => {},
+            None => {
+                let trimmed = lines[index].trim();
+
+                classified[index] = Some(match text_block {
+                    Some(closers) => match block_line(trimmed, syntax) {
+                        BlockLine::Close(closer) if closers.contains(&closer.as_str()) => {
+                            text_block = None;
+                            DiagramLine::Statement(BlockLine::Close(closer))
+                        },
+                        _ => DiagramLine::Text
+                    },
+                    None => {
+                        let block = block_line(trimmed, syntax);
+
+                        if let BlockLine::OpenText(closers) = block {
+                            text_block = Some(closers);
+                        }
+
+                        DiagramLine::Statement(block)
+                    }
+                });
+            }
+        }
+    }
+
+    classified.into_iter().flatten().collect()
+}
+
+fn diagram_syntax<'a>(kind: &str, statements: impl IntoIterator<Item = &'a str>) -> DiagramSyntax {
+    if kind != "uml" {
+        return DiagramSyntax::Other;
+    }
+
+    match statements.into_iter().any(is_activity_statement) {
+        true => DiagramSyntax::Activity,
+        false => DiagramSyntax::Uml
+    }
+}
+
+/// Statements found only in activity diagrams, e.g. `:action;`, `start` or `if (condition) then`
+fn is_activity_statement(trimmed: &str) -> bool {
+    let lowercase = trimmed.to_lowercase();
+
+    (lowercase.starts_with(':') && lowercase.ends_with([';', '|', '<', '>', '/', ']', '}']))
+        || matches!(lowercase.as_str(), "start" | "stop" | "kill" | "detach")
+        || ["if (", "if(", "while (", "while(", "repeat while", "switch (", "switch("].iter().any(|prefix| lowercase.starts_with(prefix))
+}
+
+/// Classifies a trimmed line by the block it opens or closes in a diagram of the given syntax
+fn block_line(trimmed: &str, syntax: DiagramSyntax) -> BlockLine {
+    let lowercase = trimmed.to_lowercase();
+    let words = lowercase.split_whitespace().collect::<Vec<_>>();
+    let first_word = words.first().copied().unwrap_or("");
+    let is_activity = syntax == DiagramSyntax::Activity;
+
+    if lowercase.starts_with(['\'', '!']) {
+        return BlockLine::None;
+    }
+
+    if lowercase.starts_with('}') {
+        return match lowercase.ends_with('{') {
+            true => BlockLine::Reopen,
+            false => BlockLine::Close(String::from("}"))
+        };
+    }
+
+    if lowercase.ends_with('{') {
+        return BlockLine::Open(&["}"]);
+    }
+
+    if is_activity && words.len() == 1 && ACTIVITY_STATEMENTS.contains(&first_word) {
+        return BlockLine::None;
+    }
+
+    // `end note` and `endnote` are both accepted, as are `end if` and `endif`
+    let closing_keyword = match words.as_slice() {
+        ["end", second_word, ..] if CLOSING_KEYWORDS.contains(&format!("end{second_word}").as_str()) => Some(format!("end{second_word}")),
+        [first_word, ..] if CLOSING_KEYWORDS.contains(first_word) => Some(first_word.to_string()),
+        _ => None
+    };
+
+    if let Some(closing_keyword) = closing_keyword {
+        return BlockLine::Close(closing_keyword);
+    }
+
+    // A floating note is a note not attached to an element
+    let note_words = match words.first() {
+        Some(&"floating") => &words[1..],
+        _ => &words[..]
+    };
+
+    if let Some(first_note_word) = note_words.first()
+        && let Some((_, closers)) = TEXT_BLOCKS.iter().find(|(keyword, _)| keyword == first_note_word) {
+        let is_single_line = match *first_note_word {
+            "note" | "rnote" | "hnote" | "ref" => lowercase.contains(':') || note_words.get(1).is_some_and(|word| word.starts_with('"')),
+            "title" | "header" | "footer" => note_words.len() > 1,
+            _ => false
+        };
+
+        return match is_single_line {
+            true => BlockLine::None,
+            false => BlockLine::OpenText(closers)
+        };
+    }
+
+    match (first_word, is_activity) {
+        ("else" | "also", _) => BlockLine::Reopen,
+        ("alt" | "opt" | "loop" | "par" | "par2" | "break" | "critical" | "group", false) => BlockLine::Open(&["end"]),
+        ("box", false) => BlockLine::Open(&["endbox"]),
+        ("repeat", true) if lowercase.starts_with("repeat while") || lowercase.starts_with("repeatwhile") => BlockLine::Close(String::from("repeatwhile")),
+        ("repeat", true) => BlockLine::Open(&["repeatwhile"]),
+        ("if" | "if(", true) if lowercase.ends_with("then") || lowercase.contains(") then") => BlockLine::Open(&["endif"]),
+        ("elseif" | "case", true) => BlockLine::Reopen,
+        ("while" | "while(", true) => BlockLine::Open(&["endwhile"]),
+        ("switch" | "switch(", true) => BlockLine::Open(&["endswitch"]),
+        ("group", true) => BlockLine::Open(&["endgroup"]),
+        ("partition", true) => BlockLine::Open(&["endpartition"]),
+        ("fork" | "split", true) if words.get(1) == Some(&"again") => BlockLine::Reopen,
+        ("fork", true) => BlockLine::Open(&["endfork", "endmerge"]),
+        ("split", true) => BlockLine::Open(&["endsplit"]),
+        _ => BlockLine::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_lines_of_a_note_as_free_text() {
+        let lines = ["@startuml", "note left", "alt is not a block here", "end note", "@enduml"];
+
+        assert_eq!(classify_lines(&lines), vec![
+            DiagramLine::Start(String::from("uml"), DiagramSyntax::Uml),
+            DiagramLine::Statement(BlockLine::OpenText(&["endnote"])),
+            DiagramLine::Text,
+            DiagramLine::Statement(BlockLine::Close(String::from("endnote"))),
+            DiagramLine::End,
+        ]);
+    }
+
+    #[test]
+    fn tells_the_syntax_of_each_diagram() {
+        let lines = ["@startuml", ":a;", "end", "@enduml", "@startuml", "end", "@enduml", "@startjson", "{", "}", "@endjson"];
+        let classified = classify_lines(&lines);
+
+        assert_eq!(classified[0], DiagramLine::Start(String::from("uml"), DiagramSyntax::Activity));
+        assert_eq!(classified[2], DiagramLine::Statement(BlockLine::None));
+        assert_eq!(classified[4], DiagramLine::Start(String::from("uml"), DiagramSyntax::Uml));
+        assert_eq!(classified[5], DiagramLine::Statement(BlockLine::Close(String::from("end"))));
+        assert_eq!(classified[7], DiagramLine::Start(String::from("json"), DiagramSyntax::Other));
+        assert_eq!(classified[8], DiagramLine::Statement(BlockLine::Open(&["}"])));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::app::{App, Buffer, Dialog, PendingAction};
+use crate::files::scratchpad::is_scratchpad;
+use crate::files::utils::expand_tilde;
+use anyhow::bail;
+use crokey::crossterm::event::{KeyCode, KeyModifiers};
+use crokey::OneToThree::One;
+use crokey::{key, KeyCombination};
+use std::path::{Path, PathBuf};
+use std::{env, iter, mem, path};
+
+impl<'a> App<'a> {
+    /// Open buffers, in tab order
+    pub fn buffers(&self) -> impl Iterator<Item = &Buffer<'a>> {
+        self.other_buffers[..self.buffer_index]
+            .iter()
+            .chain(iter::once(&self.buffer))
+            .chain(self.other_buffers[self.buffer_index..].iter())
+    }
+
+    /// Opens the file in a new tab after the current one, or switches to its tab if already open, and returns whether the diagram must be rendered
+    pub fn open_buffer(&mut self, input_file_path: PathBuf) -> anyhow::Result<bool> {
+        let open_buffer_index = self.buffers().position(|buffer| buffer.input_file_path == input_file_path);
+
+        if let Some(buffer_index) = open_buffer_index {
+            return self.switch_buffer(buffer_index);
+        }
+
+        let buffer = self.load_buffer(input_file_path)?;
+
+        self.save_session()?;
+        self.other_buffers.insert(self.buffer_index, mem::replace(&mut self.buffer, buffer));
+        self.buffer_index += 1;
+
+        self.apply_project_config()?;
+        self.restore_file_session();
+        self.check_swap_file();
+
+        if let Some(expanded_source) = self.expanded_source.as_mut() {
+            expanded_source.selected = 0;
+        }
+
+        Ok(true)
+    }
+
+    /// Shows another buffer, keeping the text, the preview and the pending render of the current one.
+    /// Returns whether the diagram must be rendered, its preview being missing or outdated.
+    pub fn switch_buffer(&mut self, buffer_index: usize) -> anyhow::Result<bool> {
+        if buffer_index == self.buffer_index || buffer_index > self.other_buffers.len() {
+            return Ok(false);
+        }
+
+        self.save_session()?;
+
+        let other_buffer_index = match buffer_index < self.buffer_index {
+            true => buffer_index,
+            false => buffer_index - 1
+        };
+        // The tabs before the shown one are shifted back when it comes from them
+        let previous_buffer_index = match buffer_index < self.buffer_index {
+            true => self.buffer_index - 1,
+            false => self.buffer_index
+        };
+
+        let buffer = self.other_buffers.remove(other_buffer_index);
+        let previous_buffer = mem::replace(&mut self.buffer, buffer);
+
+        self.other_buffers.insert(previous_buffer_index, previous_buffer);
+        self.buffer_index = buffer_index;
+
+        self.apply_project_config()?;
+        self.check_swap_file();
+
+        if let Some(expanded_source) = self.expanded_source.as_mut() {
+            expanded_source.selected = 0;
+            self.expand_source();
+        }
+
+        let render_output = self.buffer.render_output.read();
+        let is_rendered = render_output.pending || render_output.file_path.is_some() || render_output.render_error.is_some();
+
+        Ok(!is_rendered || render_output.dark_mode != self.dark_mode)
+    }
+
+    pub fn next_buffer(&mut self) -> anyhow::Result<bool> {
+        let buffer_count = self.other_buffers.len() + 1;
+
+        self.switch_buffer((self.buffer_index + 1) % buffer_count)
+    }
+
+    pub fn previous_buffer(&mut self) -> anyhow::Result<bool> {
+        let buffer_count = self.other_buffers.len() + 1;
+
+        self.switch_buffer((self.buffer_index + buffer_count - 1) % buffer_count)
+    }
+
+    /// Closes the current buffer, asking first what to do with the unsaved text
+    pub fn request_close_buffer(&mut self) -> anyhow::Result<bool> {
+        match self.buffer.is_dirty() {
+            true => {
+                self.dialog = Some(Dialog::UnsavedChanges { pending_action: PendingAction::Close });
+                Ok(false)
+            },
+            false => self.close_buffer()
+        }
+    }
+
+    /// Closes the current buffer, the next one being shown, and quits after the last one
+    pub fn close_buffer(&mut self) -> anyhow::Result<bool> {
+        if self.other_buffers.is_empty() {
+            self.should_quit = true;
+            return Ok(false);
+        }
+
+        self.close_swap_file();
+        self.save_session()?;
+        self.buffer.cancellation_token.cancel();
+
+        let buffer_index = self.buffer_index.min(self.other_buffers.len() - 1);
+        self.buffer = self.other_buffers.remove(buffer_index);
+        self.buffer_index = buffer_index;
+
+        self.apply_project_config()?;
+        self.check_swap_file();
+
+        Ok(true)
+    }
+
+    /// Drops the buffer of a deleted file without saving it
+    pub fn forget_buffer(&mut self, input_file_path: &Path) {
+        let Some(other_buffer_index) = self.other_buffers.iter().position(|buffer| buffer.input_file_path == input_file_path) else {
+            return;
+        };
+
+        let buffer = self.other_buffers.remove(other_buffer_index);
+        buffer.cancellation_token.cancel();
+
+        if other_buffer_index < self.buffer_index {
+            self.buffer_index -= 1;
+        }
+    }
+
+    /// Suggests the directory of the current file, the working one for a scratchpad
+    pub fn open_open_file_dialog(&mut self) {
+        let dir_path = match is_scratchpad(&self.data_dir, &self.buffer.input_file_path) {
+            true => env::current_dir().unwrap_or_default(),
+            false => self.buffer.input_file_path.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+
+        self.dialog = Some(Dialog::OpenFile {
+            path: format!("{}{}", dir_path.display(), path::MAIN_SEPARATOR),
+            error: None,
+        });
+    }
+
+    pub fn handle_open_file_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        let Some(Dialog::OpenFile { path, error }) = self.dialog.as_mut() else {
+            return Ok(false);
+        };
+
+        match key_combination {
+            key!(esc) => self.dialog = None,
+            key!(backspace) => {
+                path.pop();
+            },
+            key!(enter) => {
+                let input_file_path = path.clone();
+
+                match self.open_file(&input_file_path) {
+                    Ok(should_render) => {
+                        self.dialog = None;
+                        return Ok(should_render);
+                    },
+                    Err(err) => {
+                        if let Some(Dialog::OpenFile { error, .. }) = self.dialog.as_mut() {
+                            *error = Some(err.to_string());
+                        }
+                    }
+                }
+            },
+            KeyCombination { codes: One(KeyCode::Char(char)), modifiers } if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                path.push(char);
+                *error = None;
+            },
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    /// Opens an existing file, or a new one in an existing directory
+    fn open_file(&mut self, input_file_path: &str) -> anyhow::Result<bool> {
+        if input_file_path.trim().is_empty() {
+            bail!("The path is empty");
+        }
+
+        let input_file_path = path::absolute(expand_tilde(Path::new(input_file_path.trim())))?;
+
+        if input_file_path.is_dir() {
+            bail!("\"{}\" is a directory", input_file_path.display());
+        }
+
+        if !input_file_path.exists() && !input_file_path.parent().is_some_and(Path::is_dir) {
+            bail!("The directory of \"{}\" does not exist", input_file_path.display());
+        }
+
+        self.open_buffer(input_file_path)
+    }
+}
@@ -0,0 +1,69 @@
+use crate::files::pmu::split_diagram_blocks;
+use crate::render::{describe_parse_error, parse_plantuml_error, syntax_command, PlantUmlOptions};
+use plantuml_parser::PlantUmlFileData;
+use serde::Serialize;
+use std::path::Path;
+
+/// Outcome of the syntax validation of a file, without rendering it
+#[derive(Serialize)]
+pub struct CheckResult {
+    pub diagrams: Vec<DiagramCheck>,
+    pub errors: Vec<CheckError>,
+}
+
+#[derive(Serialize)]
+pub struct DiagramCheck {
+    /// Line (1-based) of the start keyword
+    pub line: usize,
+    /// Diagram type detected by PlantUML, e.g. `sequence` or `class`
+    #[serde(rename = "type")]
+    pub diagram_type: String,
+}
+
+#[derive(Serialize)]
+pub struct CheckError {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Validates the structure with `plantuml_parser`, then each diagram with PlantUML's `-syntax` mode
+pub async fn check_plantuml(content: &str, working_dir_path: &Path, plantuml_options: &PlantUmlOptions) -> anyhow::Result<CheckResult> {
+    let mut result = CheckResult {
+        diagrams: vec![],
+        errors: vec![],
+    };
+
+    if let Err(parse_error) = PlantUmlFileData::parse_from_str(content) {
+        let (line, message) = describe_parse_error(&parse_error, content);
+        result.errors.push(CheckError { line, message });
+
+        return Ok(result);
+    }
+
+    for block in split_diagram_blocks(content) {
+        let output = syntax_command(plantuml_options, block.source.as_bytes(), working_dir_path).await?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let first_line = stdout.lines().map(str::trim).find(|line| !line.is_empty());
+
+        match first_line {
+            Some("ERROR") => {
+                let (line, message) = parse_plantuml_error(&stdout);
+
+                result.errors.push(CheckError {
+                    line: line.map(|line| line + block.start_line),
+                    message,
+                });
+            },
+            Some(diagram_type) => result.diagrams.push(DiagramCheck {
+                line: block.start_line + 1,
+                diagram_type: diagram_type.to_lowercase(),
+            }),
+            None => result.errors.push(CheckError {
+                line: Some(block.start_line + 1),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        }
+    }
+
+    Ok(result)
+}
@@ -7,7 +7,7 @@ impl App<'_> {
     pub fn copy_to_clipboard(&self) -> anyhow::Result<()> {
         let mut clipboard = Clipboard::new()?;
 
-        let render_output = self.render_output.read();
+        let render_output = self.buffer.render_output.read();
 
         if let Some(file_path) = &render_output.file_path {
             let content = fs::read(file_path)?;
@@ -0,0 +1,32 @@
+use crate::app::{App, Dialog};
+use crokey::KeyCombination;
+
+impl App<'_> {
+    /// Handles a key while a dialog is shown, returning whether the diagram must be rendered again
+    pub fn handle_dialog_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        match &self.dialog {
+            Some(Dialog::Scratchpads { .. }) => self.handle_scratchpads_event(key_combination),
+            Some(Dialog::SaveAs { .. }) => self.handle_save_as_event(key_combination),
+            Some(Dialog::OpenFile { .. }) => self.handle_open_file_event(key_combination),
+            Some(Dialog::Recover { .. }) => self.handle_recover_event(key_combination),
+            Some(Dialog::EmbeddedDiagrams { .. }) => self.handle_embedded_diagrams_event(key_combination),
+            Some(Dialog::UnsavedChanges { .. }) => self.handle_unsaved_changes_event(key_combination),
+            None => Ok(false)
+        }
+    }
+}
+
+impl Dialog {
+    /// Keys of the dialog, shown at its bottom
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Dialog::Scratchpads { .. } => " enter: open  n: new  del: delete  esc: close ",
+            Dialog::SaveAs { .. } => " enter: save  esc: cancel ",
+            Dialog::OpenFile { .. } => " enter: open  esc: cancel ",
+            Dialog::Recover { diff: None, .. } => " r: recover  d: show diff  x: discard ",
+            Dialog::Recover { diff: Some(_), .. } => " r: recover  d: hide diff  x: discard  ↑↓: scroll ",
+            Dialog::EmbeddedDiagrams { .. } => " enter: edit  esc: close ",
+            Dialog::UnsavedChanges { .. } => " s: save  d: discard  esc: cancel ",
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::app::{App, Dialog, PendingAction};
+use crokey::{key, KeyCombination};
+
+impl App<'_> {
+    pub fn open_embedded_diagrams_dialog(&mut self) {
+        if let Some(document) = &self.buffer.embedded {
+            self.dialog = Some(Dialog::EmbeddedDiagrams { selected: document.block_index });
+        }
+    }
+
+    pub fn handle_embedded_diagrams_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        let Some(Dialog::EmbeddedDiagrams { selected }) = self.dialog.as_mut() else {
+            return Ok(false);
+        };
+
+        let block_count = self.buffer.embedded.as_ref().map_or(0, |document| document.blocks.len());
+
+        match key_combination {
+            key!(esc) => self.dialog = None,
+            key!(up) => *selected = selected.saturating_sub(1),
+            key!(down) => *selected = (*selected + 1).min(block_count.saturating_sub(1)),
+            key!(enter) => {
+                let block_index = *selected;
+
+                match self.buffer.is_dirty() {
+                    true => self.dialog = Some(Dialog::UnsavedChanges { pending_action: PendingAction::SwitchEmbeddedDiagram(block_index) }),
+                    false => {
+                        self.dialog = None;
+                        return Ok(self.switch_embedded_diagram(block_index));
+                    }
+                }
+            },
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    /// Shows another diagram of the document in the editor, the unsaved text of the current one being dropped.
+    /// Returns whether the diagram must be rendered.
+    pub fn switch_embedded_diagram(&mut self, block_index: usize) -> bool {
+        let Some(document) = self.buffer.embedded.as_mut() else {
+            return false;
+        };
+
+        if block_index == document.block_index || block_index >= document.blocks.len() {
+            return false;
+        }
+
+        document.block_index = block_index;
+        let source = document.block().source.clone();
+
+        self.close_swap_file();
+        self.buffer.text_input.text = source.clone();
+        self.buffer.text_input.set_cursor_position((0, 0));
+        self.buffer.saved_text = source;
+
+        true
+    }
+}
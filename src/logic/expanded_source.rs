@@ -0,0 +1,148 @@
+use crate::app::{App, ExpandedSource, ExpandedSourceView};
+use crate::files::include::{inline_includes, ExpandedLine};
+use crate::render::{effective_plantuml_options, preproc_command};
+use crokey::{key, KeyCombination};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+/// Lines searched ahead for the origin of a preprocessed line, the lines in between being consumed by the preprocessor
+const ORIGIN_LOOKAHEAD: usize = 100;
+
+impl App<'_> {
+    /// Focuses the expanded source, showing it when hidden, and hides it when focused
+    pub fn toggle_expanded_source(&mut self) {
+        match &mut self.expanded_source {
+            Some(expanded_source) if expanded_source.focused => self.expanded_source = None,
+            Some(expanded_source) => expanded_source.focused = true,
+            None => {
+                self.expanded_source = Some(ExpandedSourceView { selected: 0, focused: true });
+                self.expand_source();
+            }
+        }
+
+        if let Some(sidebar) = self.sidebar.as_mut() {
+            sidebar.focused = false;
+        }
+    }
+
+    /// Inlines the includes right away, then replaces them with the output of PlantUML's preprocessor once done
+    pub fn expand_source(&mut self) {
+        let inlined_lines = inline_includes(&self.buffer.input_file_path, &self.buffer.text_input.text);
+
+        *self.buffer.expanded_source.write() = ExpandedSource {
+            lines: inlined_lines.clone(),
+            preprocessed: false,
+        };
+
+        let expanded_source_clone = self.buffer.expanded_source.clone();
+        let text_input = self.buffer.text_input.text.clone();
+        let working_dir_path = self.buffer.input_file_path.parent().unwrap_or(&self.data_dir).to_path_buf();
+        let plantuml_options = effective_plantuml_options(&self.buffer.project_config);
+        let cancellation_token = self.buffer.cancellation_token.clone();
+
+        tokio::spawn(async move {
+            tokio::select! {
+                output = preproc_command(&plantuml_options, text_input.as_bytes(), &working_dir_path) => {
+                    if let Ok(output) = output
+                        && output.status.success() {
+                        set_preprocessed_source(&expanded_source_clone, &String::from_utf8_lossy(&output.stdout), &inlined_lines);
+                    }
+                },
+                _ = cancellation_token.cancelled() => {},
+            }
+        });
+    }
+
+    pub fn handle_expanded_source_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        let Some(expanded_source) = self.expanded_source.as_mut() else {
+            return Ok(false);
+        };
+
+        let line_count = self.buffer.expanded_source.read().lines.len();
+
+        match key_combination {
+            key!(esc) => expanded_source.focused = false,
+            key!(up) => expanded_source.selected = expanded_source.selected.saturating_sub(1),
+            key!(down) => expanded_source.selected = (expanded_source.selected + 1).min(line_count.saturating_sub(1)),
+            key!(pageup) => expanded_source.selected = expanded_source.selected.saturating_sub(10),
+            key!(pagedown) => expanded_source.selected = (expanded_source.selected + 10).min(line_count.saturating_sub(1)),
+            key!(enter) => {
+                let selected = expanded_source.selected;
+                let expanded_line = self.buffer.expanded_source.read().lines.get(selected).cloned();
+
+                if let Some(expanded_line) = expanded_line {
+                    expanded_source.focused = false;
+                    return self.go_to_origin(expanded_line);
+                }
+            },
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    /// Moves the cursor to the line an expanded line comes from, opening the included file it is in
+    fn go_to_origin(&mut self, expanded_line: ExpandedLine) -> anyhow::Result<bool> {
+        let mut should_render = false;
+
+        if expanded_line.file_path != self.buffer.input_file_path {
+            let including_file_path = self.buffer.input_file_path.clone();
+
+            should_render = self.open_buffer(expanded_line.file_path)?;
+            self.buffer.included_from.get_or_insert(including_file_path);
+        }
+
+        let column = self.buffer.text_input.cursor_position.1;
+        self.buffer.text_input.set_cursor_position((expanded_line.line as u16, column));
+
+        Ok(should_render)
+    }
+}
+
+/// Maps each line of the preprocessor output to the inlined line it comes from, in order.
+/// A line produced by a definition or a procedure has no exact match and gets the origin of the next line not consumed by the preprocessor, most likely its call.
+fn set_preprocessed_source(expanded_source: &Arc<RwLock<ExpandedSource>>, preprocessed_text: &str, inlined_lines: &[ExpandedLine]) {
+    if inlined_lines.is_empty() {
+        return;
+    }
+
+    let mut lines = vec![];
+    let mut inlined_index = 0;
+
+    for preprocessed_line in preprocessed_text.lines() {
+        let trimmed = preprocessed_line.trim();
+        let lookahead_end = (inlined_index + ORIGIN_LOOKAHEAD).min(inlined_lines.len());
+
+        let matched_index = match trimmed.is_empty() {
+            true => None,
+            false => (inlined_index..lookahead_end).find(|index| inlined_lines[*index].text.trim() == trimmed)
+        };
+
+        let origin_index = match matched_index {
+            Some(matched_index) => {
+                inlined_index = matched_index + 1;
+                matched_index
+            },
+            None => (inlined_index..lookahead_end)
+                .find(|index| !is_consumed_by_preprocessor(&inlined_lines[*index].text))
+                .unwrap_or(inlined_index.min(inlined_lines.len() - 1))
+        };
+
+        lines.push(ExpandedLine {
+            text: preprocessed_line.to_string(),
+            ..inlined_lines[origin_index].clone()
+        });
+    }
+
+    *expanded_source.write() = ExpandedSource {
+        lines,
+        preprocessed: true,
+    };
+}
+
+/// Directives, comments and blank lines, which don't appear in the preprocessor output
+fn is_consumed_by_preprocessor(line: &str) -> bool {
+    let trimmed = line.trim_start();
+
+    trimmed.is_empty() || trimmed.starts_with('!') || trimmed.starts_with('\'')
+}
@@ -0,0 +1,326 @@
+use crate::app::App;
+use crate::logic::blocks::{classify_lines, BlockLine, DiagramLine};
+
+const ARROW_HEADS: [&str; 14] = ["<|", "<<", "\\\\", "//", "<", "o", "x", "*", "+", "#", "{", "}", "\\", "/"];
+const ARROW_TAILS: [&str; 14] = ["|>", ">>", "\\\\", "//", ">", "o", "x", "*", "+", "#", "{", "}", "\\", "/"];
+const ARROW_DIRECTIONS: [&str; 8] = ["up", "down", "left", "right", "u", "d", "l", "r"];
+
+impl App<'_> {
+    pub fn format_text_input(&mut self) {
+        let formatted = format_plantuml(&self.buffer.text_input.text, self.buffer.project_config.config.editor.tab_width);
+
+        if formatted == self.buffer.text_input.text {
+            return;
+        }
+
+        self.buffer.text_input.text = formatted;
+
+        let line_count = self.buffer.text_input.text.lines().count().max(1) as u16;
+        self.buffer.text_input.cursor_position.0 = self.buffer.text_input.cursor_position.0.min(line_count - 1);
+        self.buffer.text_input.cursor_position.1 = self.buffer.text_input.clamp_cursor_width(self.buffer.text_input.cursor_position.0, self.buffer.text_input.cursor_position.1);
+    }
+}
+
+/// Indents the blocks with `tab_width` spaces per level and normalizes the spacing around arrows and blank lines, keeping the line endings
+pub fn format_plantuml(input: &str, tab_width: usize) -> String {
+    let line_ending = match input.contains("\r\n") {
+        true => "\r\n",
+        false => "\n"
+    };
+
+    let mut lines: Vec<String> = vec![];
+    let mut depth = 0usize;
+    let raw_lines = input.lines().collect::<Vec<_>>();
+
+    for (raw_line, diagram_line) in raw_lines.iter().zip(classify_lines(&raw_lines)) {
+        let line = raw_line.trim_end();
+        let trimmed = line.trim_start();
+
+        match diagram_line {
+            DiagramLine::Start(_, _) | DiagramLine::End => {
+                depth = 0;
+                lines.push(trimmed.to_string());
+            },
+            DiagramLine::Comment => lines.push(line.to_string()),
+            // Notes, legends and titles hold free text that must not be reformatted
+            DiagramLine::CommentOpen | DiagramLine::Include(_) | DiagramLine::Empty | DiagramLine::Text => lines.push(indent(depth, tab_width, trimmed)),
+            DiagramLine::Statement(BlockLine::Open(_)) => {
+                lines.push(indent(depth, tab_width, &format_arrows(trimmed)));
+                depth += 1;
+            },
+            DiagramLine::Statement(BlockLine::OpenText(_)) => {
+                lines.push(indent(depth, tab_width, trimmed));
+                depth += 1;
+            },
+            DiagramLine::Statement(BlockLine::Close(_)) => {
+                depth = depth.saturating_sub(1);
+                lines.push(indent(depth, tab_width, trimmed));
+            },
+            DiagramLine::Statement(BlockLine::Reopen) => lines.push(indent(depth.saturating_sub(1), tab_width, trimmed)),
+            DiagramLine::Statement(BlockLine::None) => lines.push(indent(depth, tab_width, &format_arrows(trimmed))),
+        }
+    }
+
+    let mut formatted = normalize_blank_lines(lines).join(line_ending);
+
+    if input.ends_with('\n') && !formatted.is_empty() {
+        formatted.push_str(line_ending);
+    }
+
+    formatted
+}
+
+fn indent(depth: usize, tab_width: usize, line: &str) -> String {
+    if line.is_empty() {
+        return String::new();
+    }
+
+    format!("{}{}", " ".repeat(depth * tab_width), line)
+}
+
+/// Collapses consecutive blank lines and removes the ones at the edges of each diagram
+fn normalize_blank_lines(lines: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = vec![];
+
+    for line in lines {
+        let trimmed = line.trim_start();
+
+        if line.is_empty() {
+            match normalized.last() {
+                None => continue,
+                Some(previous) if previous.is_empty() || previous.trim_start().starts_with("@start") => continue,
+                _ => {}
+            }
+        }
+        else if trimmed.starts_with("@end") {
+            while normalized.last().is_some_and(|previous| previous.is_empty()) {
+                normalized.pop();
+            }
+        }
+        else if trimmed.starts_with("@start") && normalized.last().is_some_and(|previous| previous.trim_start().starts_with("@end")) {
+            normalized.push(String::new());
+        }
+
+        normalized.push(line);
+    }
+
+    while normalized.last().is_some_and(|line| line.is_empty()) {
+        normalized.pop();
+    }
+
+    normalized
+}
+
+/// Puts exactly one space on each side of the first arrow of a line, e.g. `A->B: Hello` becomes `A -> B: Hello`
+fn format_arrows(line: &str) -> String {
+    if line.starts_with("skinparam") || line.starts_with([':', '=', '|', '\'', '!']) || line.starts_with("...") {
+        return line.to_string();
+    }
+
+    let head_length = head_segment_length(line);
+    let (head, rest) = line.split_at(head_length);
+
+    let Some((start, end)) = find_arrow(head) else {
+        return line.to_string();
+    };
+
+    let before = head[..start].trim_end();
+    let arrow = &head[start..end];
+    let after = head[end..].trim_start();
+
+    let mut formatted = String::new();
+
+    if !before.is_empty() {
+        formatted.push_str(before);
+        formatted.push(' ');
+    }
+
+    formatted.push_str(arrow);
+
+    if !after.is_empty() {
+        formatted.push(' ');
+        formatted.push_str(after);
+    }
+    else if !rest.is_empty() {
+        formatted = formatted.trim_end().to_string();
+    }
+
+    formatted.push_str(rest);
+    formatted
+}
+
+/// Length of the part of the line before the message, i.e. before the first `:` outside quotes
+fn head_segment_length(line: &str) -> usize {
+    let mut in_quotes = false;
+
+    for (index, char) in line.char_indices() {
+        match char {
+            '"' => in_quotes = !in_quotes,
+            ':' if !in_quotes => return index,
+            _ => {}
+        }
+    }
+
+    line.len()
+}
+
+/// Returns the byte range of the first arrow found in the given segment
+fn find_arrow(segment: &str) -> Option<(usize, usize)> {
+    let mut in_quotes = false;
+    let mut token_start = None;
+
+    // Arrows already separated by whitespace, which may use letters for their heads (e.g. `A o-- B`)
+    for (index, char) in segment.char_indices().chain([(segment.len(), ' ')]) {
+        if char == '"' {
+            in_quotes = !in_quotes;
+        }
+
+        if char.is_whitespace() && !in_quotes {
+            if let Some(start) = token_start.take()
+                && is_arrow(&segment[start..index]) {
+                return Some((start, index));
+            }
+        }
+        else if token_start.is_none() {
+            token_start = Some(index);
+        }
+    }
+
+    // Arrows glued to participants (e.g. `A->B`), restricted to symbolic heads to avoid eating names
+    let bytes = segment.as_bytes();
+    let mut in_quotes = false;
+
+    for start in 0..segment.len() {
+        if bytes[start] == b'"' {
+            in_quotes = !in_quotes;
+        }
+
+        if in_quotes || !segment.is_char_boundary(start) || !matches!(bytes[start], b'<' | b'-' | b'.') {
+            continue;
+        }
+
+        for end in (start + 2..=segment.len()).rev() {
+            if !segment.is_char_boundary(end) {
+                continue;
+            }
+
+            let candidate = &segment[start..end];
+            let symbolic_tail = !candidate.ends_with(['o', 'x']);
+
+            if symbolic_tail && is_arrow(candidate) {
+                return Some((start, end));
+            }
+        }
+    }
+
+    None
+}
+
+fn is_arrow(token: &str) -> bool {
+    let mut body = token;
+    let mut has_tip = false;
+
+    if let Some(head) = ARROW_HEADS.iter().find(|head| body.starts_with(*head)) {
+        body = &body[head.len()..];
+        has_tip = true;
+    }
+
+    if let Some(tail) = ARROW_TAILS.iter().find(|tail| body.ends_with(*tail)) {
+        body = &body[..body.len() - tail.len()];
+        has_tip = true;
+    }
+
+    if body.is_empty() || !body.starts_with(['-', '.']) || !body.ends_with(['-', '.', ']']) {
+        return false;
+    }
+
+    let mut rest = body;
+    let mut line_chars = 0;
+
+    while !rest.is_empty() {
+        if rest.starts_with(['-', '.']) {
+            line_chars += 1;
+            rest = &rest[1..];
+        }
+        else if rest.starts_with('[') {
+            match rest.find(']') {
+                Some(close) => rest = &rest[close + 1..],
+                None => return false
+            }
+        }
+        else if let Some(direction) = ARROW_DIRECTIONS.iter().find(|direction| rest.starts_with(*direction)) {
+            rest = &rest[direction.len()..];
+
+            if !rest.starts_with(['-', '.']) {
+                return false;
+            }
+        }
+        else {
+            return false;
+        }
+    }
+
+    has_tip || line_chars >= 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indents_sequence_blocks() {
+        let input = "@startuml\nAlice->Bob: Hello\nalt success\nBob-->Alice: OK\nelse failure\nbreak timeout\nBob->Alice: Retry\nend\nend\nnote left\n  free   text\nend note\n@enduml\n";
+        let expected = "@startuml\nAlice -> Bob: Hello\nalt success\n  Bob --> Alice: OK\nelse failure\n  break timeout\n    Bob -> Alice: Retry\n  end\nend\nnote left\n  free   text\nend note\n@enduml\n";
+
+        assert_eq!(format_plantuml(input, 2), expected);
+    }
+
+    #[test]
+    fn keeps_break_and_end_as_activity_statements() {
+        let input = "@startuml\nstart\nrepeat\n:read;\nif (eof?) then (yes)\nbreak\nendif\n:process;\nrepeat while (more?)\nfork\n:a;\nfork again\n:b;\nend fork\nend\n@enduml\n";
+        let expected = "@startuml\nstart\nrepeat\n  :read;\n  if (eof?) then (yes)\n    break\n  endif\n  :process;\nrepeat while (more?)\nfork\n  :a;\nfork again\n  :b;\nend fork\nend\n@enduml\n";
+
+        assert_eq!(format_plantuml(input, 2), expected);
+        assert_eq!(format_plantuml(expected, 2), expected);
+    }
+
+    #[test]
+    fn tells_the_diagram_kind_of_each_diagram() {
+        let input = "@startuml\n:a;\nbreak\n@enduml\n@startuml\nloop\nA -> B\nbreak\nB -> A\nend\nend\n@enduml\n";
+        let expected = "@startuml\n:a;\nbreak\n@enduml\n\n@startuml\nloop\n  A -> B\n  break\n    B -> A\n  end\nend\n@enduml\n";
+
+        assert_eq!(format_plantuml(input, 2), expected);
+    }
+
+    #[test]
+    fn normalizes_blank_lines_and_arrows() {
+        let input = "@startuml\n\n\nA->B : hi\n\n\nB<--A\n\n@enduml";
+
+        assert_eq!(format_plantuml(input, 4), "@startuml\nA -> B : hi\n\nB <-- A\n@enduml");
+    }
+
+    #[test]
+    fn indents_partition_and_box_blocks() {
+        let input = "@startuml\nstart\npartition Init\n:a;\nend partition\nfork\n:b;\nend merge\nstop\n@enduml\n@startuml\nbox \"Internal\" #LightBlue\nparticipant A\nend box\ngroup setup\nA->A\nend\n@enduml\n";
+        let expected = "@startuml\nstart\npartition Init\n  :a;\nend partition\nfork\n  :b;\nend merge\nstop\n@enduml\n\n@startuml\nbox \"Internal\" #LightBlue\n  participant A\nend box\ngroup setup\n  A -> A\nend\n@enduml\n";
+
+        assert_eq!(format_plantuml(input, 2), expected);
+        assert_eq!(format_plantuml(expected, 2), expected);
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let input = "@startuml\r\nalt ok\r\nA->B\r\nend\r\n@enduml\r\n";
+        let expected = "@startuml\r\nalt ok\r\n  A -> B\r\nend\r\n@enduml\r\n";
+
+        assert_eq!(format_plantuml(input, 2), expected);
+        assert_eq!(format_plantuml(expected, 2), expected);
+    }
+
+    #[test]
+    fn leaves_the_text_of_titles_and_legends_alone() {
+        let input = "@startuml\ntitle Flow A->B\nheader a->b\nlegend\nx->y\nendlegend\n@enduml\n";
+
+        assert_eq!(format_plantuml(input, 2), "@startuml\ntitle Flow A->B\nheader a->b\nlegend\n  x->y\nendlegend\n@enduml\n");
+    }
+}
@@ -0,0 +1,92 @@
+use crate::app::App;
+use crate::files::include::{included_file_paths, line_include_path, resolve_include};
+use std::fs;
+use std::iter;
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+
+impl App<'_> {
+    /// Opens the file included on the cursor line, remembering where it was included from
+    pub fn go_to_include(&mut self) -> anyhow::Result<bool> {
+        let cursor_line = self.buffer.text_input.cursor_position.0 as usize;
+
+        let Some(include_path) = self.buffer.text_input.text.lines().nth(cursor_line).and_then(line_include_path) else {
+            return Ok(false);
+        };
+
+        let Some(included_file_path) = resolve_include(&self.buffer.input_file_path, &include_path).filter(|path| path.is_file()) else {
+            return Ok(false);
+        };
+
+        let including_file_path = self.buffer.input_file_path.clone();
+        let should_render = self.open_buffer(included_file_path)?;
+
+        if self.buffer.input_file_path != including_file_path {
+            self.buffer.included_from = Some(including_file_path);
+        }
+
+        Ok(should_render)
+    }
+
+    /// Goes back to the file including the current one
+    pub fn go_back(&mut self) -> anyhow::Result<bool> {
+        match self.buffer.included_from.clone() {
+            Some(including_file_path) => self.open_buffer(including_file_path),
+            None => Ok(false)
+        }
+    }
+
+    /// Files followed through `!include`s down to the current one, the root first
+    pub fn include_trail(&self) -> Vec<String> {
+        let mut include_trail = vec![self.buffer.file_name()];
+        let mut included_from = self.buffer.included_from.as_ref();
+
+        // A cycle of includes is cut once every buffer has been visited
+        while let Some(including_file_path) = included_from
+            && include_trail.len() <= self.other_buffers.len() + 1 {
+            include_trail.push(including_file_path.file_name().unwrap_or_default().to_string_lossy().to_string());
+
+            included_from = self.buffers()
+                .find(|buffer| buffer.input_file_path == *including_file_path)
+                .and_then(|buffer| buffer.included_from.as_ref());
+        }
+
+        include_trail.reverse();
+        include_trail
+    }
+
+    /// Remembers the files included by the diagram being rendered, with their current modification time
+    pub fn watch_included_files(&mut self) {
+        self.buffer.included_files = included_file_paths(&self.buffer.input_file_path, &self.buffer.text_input.text)
+            .into_iter()
+            .map(|included_file_path| {
+                let modified = modified_time(&included_file_path);
+                (included_file_path, modified)
+            })
+            .collect();
+    }
+
+    /// Renders a diagram again when one of its included files changed on disk, the hidden ones when shown again
+    pub fn check_included_files(&mut self) {
+        for buffer in iter::once(&mut self.buffer).chain(self.other_buffers.iter_mut()) {
+            let mut is_outdated = false;
+
+            for (included_file_path, modified) in buffer.included_files.iter_mut() {
+                let current_modified = modified_time(included_file_path);
+
+                if current_modified != *modified {
+                    *modified = current_modified;
+                    is_outdated = true;
+                }
+            }
+
+            if is_outdated && buffer.render_requested_at.is_none() {
+                buffer.render_requested_at = Some(Instant::now());
+            }
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
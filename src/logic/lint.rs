@@ -0,0 +1,526 @@
+use crate::app::App;
+use crate::files::embedded::wrap_diagram_source;
+use crate::files::include::resolve_include;
+use crate::logic::blocks::{classify_lines, BlockLine, DiagramLine, DiagramSyntax};
+use plantuml_parser::{Error, PlantUmlFileData};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use strum::Display;
+
+const DECLARATION_KEYWORDS: [&str; 40] = [
+    "participant", "actor", "boundary", "control", "entity", "database", "collections", "queue",
+    "usecase", "class", "interface", "abstract", "annotation", "enum", "struct", "protocol", "exception", "object", "map", "json",
+    "component", "node", "cloud", "label", "rectangle", "package", "folder", "frame", "artifact", "storage",
+    "card", "agent", "file", "stack", "state", "circle", "person", "hexagon", "port", "action",
+];
+
+const ACTIVATION_MARKERS: [&str; 4] = ["++", "--", "**", "!!"];
+
+#[derive(Serialize, Deserialize, Display, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum LintRule {
+    UndeclaredParticipant,
+    DuplicateAlias,
+    UnmatchedBlock,
+    MissingInclude,
+    UnusedDefine,
+    MissingEnd,
+}
+
+#[derive(Serialize, Deserialize, Display, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Severity {
+    Off,
+    Warning,
+    Error,
+}
+
+/// Severity of each rule, as configured in the `[lint]` section of the project configuration
+#[derive(Deserialize, Default, Clone)]
+#[serde(transparent)]
+pub struct LintConfig {
+    rules: HashMap<LintRule, Severity>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LintDiagnostic {
+    /// 1-based line number
+    pub line: usize,
+    pub severity: Severity,
+    pub rule: LintRule,
+    pub message: String,
+}
+
+impl LintRule {
+    fn default_severity(&self) -> Severity {
+        match self {
+            LintRule::UnmatchedBlock | LintRule::MissingInclude | LintRule::MissingEnd => Severity::Error,
+            LintRule::UndeclaredParticipant | LintRule::DuplicateAlias | LintRule::UnusedDefine => Severity::Warning,
+        }
+    }
+}
+
+impl LintConfig {
+    pub fn severity(&self, rule: LintRule) -> Severity {
+        self.rules.get(&rule).copied().unwrap_or(rule.default_severity())
+    }
+}
+
+impl App<'_> {
+    pub fn lint_text_input(&mut self) {
+        if !self.buffer.project_config.config.editor.live_lint {
+            self.buffer.lint_diagnostics.clear();
+            return;
+        }
+
+        // The diagram of a document may leave out `@startuml`, the diagnostics being shifted back to the editor lines
+        let (text, line_offset) = match &self.buffer.embedded {
+            Some(_) => wrap_diagram_source(&self.buffer.text_input.text),
+            None => (self.buffer.text_input.text.clone(), 0)
+        };
+
+        self.buffer.lint_diagnostics = lint_plantuml(&text, &self.buffer.input_file_path, &self.buffer.project_config.lint)
+            .into_iter()
+            .map(|diagnostic| LintDiagnostic {
+                line: diagnostic.line.saturating_sub(line_offset).max(1),
+                ..diagnostic
+            })
+            .collect();
+    }
+}
+
+/// Lines of a diagram, as split by the PlantUML parser
+struct Diagram<'a> {
+    start_line: usize,
+    kind: String,
+    syntax: DiagramSyntax,
+    end_line: Option<usize>,
+    /// Trimmed statements between the `@start` and `@end` lines with their index, comments, free text and preprocessor lines left out
+    body: Vec<(usize, &'a str, BlockLine)>,
+}
+
+/// Block opened in a diagram, waiting for one of its closing keywords
+struct OpenBlock {
+    line_index: usize,
+    opening: String,
+    closers: &'static [&'static str],
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: LintRule, line_index: usize, message: String) {
+        let severity = self.config.severity(rule);
+
+        if severity == Severity::Off {
+            return;
+        }
+
+        self.diagnostics.push(LintDiagnostic {
+            line: line_index + 1,
+            severity,
+            rule,
+            message,
+        });
+    }
+}
+
+pub fn lint_plantuml(text: &str, file_path: &Path, config: &LintConfig) -> Vec<LintDiagnostic> {
+    let mut linter = Linter {
+        config,
+        diagnostics: vec![],
+    };
+
+    let lines = text.lines().collect::<Vec<_>>();
+    let mut diagrams: Vec<Diagram> = vec![];
+    let mut defines: Vec<(usize, String, String)> = vec![];
+
+    for ((line_index, line), diagram_line) in lines.iter().enumerate().zip(classify_lines(&lines)) {
+        let trimmed = line.trim();
+        let current_diagram = diagrams.last_mut().filter(|diagram| diagram.end_line.is_none());
+
+        match diagram_line {
+            // A diagram started before the previous one was closed is left unclosed
+            DiagramLine::Start(kind, syntax) => diagrams.push(Diagram {
+                start_line: line_index,
+                kind,
+                syntax,
+                end_line: None,
+                body: vec![],
+            }),
+            DiagramLine::End => if let Some(diagram) = current_diagram {
+                diagram.end_line = Some(line_index);
+            },
+            DiagramLine::Include(include_path) => {
+                lint_include(&mut linter, line_index, &include_path, &defines, file_path);
+            },
+            DiagramLine::CommentOpen | DiagramLine::Comment | DiagramLine::Empty | DiagramLine::Text => {},
+            DiagramLine::Statement(block) => {
+                if let Some(define) = trimmed.strip_prefix("!define ").or_else(|| trimmed.strip_prefix("!definelong ")) {
+                    let define = define.trim_start();
+                    let name_length = define.find(|char: char| char.is_whitespace() || char == '(').unwrap_or(define.len());
+                    let (name, value) = define.split_at(name_length);
+                    defines.push((line_index, name.to_string(), value.trim().to_string()));
+                }
+                else if let Some(diagram) = current_diagram
+                    && !trimmed.starts_with(['!', '\'']) {
+                    diagram.body.push((line_index, trimmed, block));
+                }
+            }
+        }
+    }
+
+    for (index, diagram) in diagrams.iter().enumerate() {
+        // An unclosed diagram goes on up to the next one
+        let last_line = match diagram.end_line {
+            Some(end_line) => end_line,
+            None => diagrams.get(index + 1).map(|next_diagram| next_diagram.start_line - 1).unwrap_or(lines.len() - 1)
+        };
+
+        lint_diagram_end(&mut linter, diagram, &lines[diagram.start_line..=last_line]);
+
+        if diagram.syntax != DiagramSyntax::Other {
+            lint_diagram_body(&mut linter, diagram);
+        }
+    }
+
+    for (line_index, name, _) in &defines {
+        let used = lines
+            .iter()
+            .enumerate()
+            .any(|(other_line_index, line)| other_line_index != *line_index && contains_word(line, name));
+
+        if !used {
+            linter.report(LintRule::UnusedDefine, *line_index, format!("\"{name}\" is defined but never used"));
+        }
+    }
+
+    linter.diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    linter.diagnostics
+}
+
+/// Reports a diagram without end keyword, or closed by the end keyword of another diagram kind, as the PlantUML parser sees it
+fn lint_diagram_end(linter: &mut Linter, diagram: &Diagram, diagram_lines: &[&str]) {
+    let mut diagram_text = diagram_lines.join("\n");
+    diagram_text.push('\n');
+
+    match PlantUmlFileData::parse_from_str(diagram_text) {
+        Ok(file_data) if !file_data.is_empty() => {},
+        Ok(_) | Err(Error::ContentUnclosed(_)) => {
+            linter.report(LintRule::MissingEnd, diagram.start_line, String::from("Diagram is never closed by an end keyword"));
+        },
+        Err(Error::DiagramKindNotMatch(_, _)) => {
+            let end_line = diagram_lines[diagram_lines.len() - 1];
+            linter.report(LintRule::MissingEnd, diagram.start_line + diagram_lines.len() - 1, format!("\"{}\" does not close a \"@start{}\" diagram", end_line.trim(), diagram.kind));
+        },
+        Err(_) => {}
+    }
+}
+
+/// Checks the blocks of the diagram, and its declarations and messages
+fn lint_diagram_body(linter: &mut Linter, diagram: &Diagram) {
+    let mut blocks: Vec<OpenBlock> = vec![];
+    let mut declared = HashSet::new();
+    let mut aliases = HashMap::new();
+    let mut messages = vec![];
+
+    for (line_index, trimmed, block_line) in &diagram.body {
+        let (line_index, trimmed) = (*line_index, *trimmed);
+
+        match block_line {
+            BlockLine::Open(closers) | BlockLine::OpenText(closers) => blocks.push(OpenBlock {
+                line_index,
+                opening: trimmed.to_string(),
+                closers,
+            }),
+            BlockLine::Close(closer) => match blocks.pop() {
+                None => linter.report(LintRule::UnmatchedBlock, line_index, format!("\"{trimmed}\" does not close any block")),
+                Some(block) if !block.closers.contains(&closer.as_str()) => {
+                    linter.report(LintRule::UnmatchedBlock, line_index, format!("\"{trimmed}\" closes \"{}\" from line {}", block.opening, block.line_index + 1));
+                },
+                Some(_) => {}
+            },
+            BlockLine::Reopen => if blocks.is_empty() {
+                linter.report(LintRule::UnmatchedBlock, line_index, format!("\"{trimmed}\" is outside of any block"));
+            },
+            BlockLine::None => {
+                lint_declaration(linter, &mut declared, &mut aliases, line_index, trimmed);
+
+                if diagram.syntax == DiagramSyntax::Uml
+                    && let Some((left, right)) = message_participants(trimmed) {
+                    messages.extend([left, right].into_iter().flatten().map(|participant| (line_index, participant)));
+                }
+            }
+        }
+    }
+
+    for block in blocks {
+        linter.report(LintRule::UnmatchedBlock, block.line_index, format!("\"{}\" is never closed", block.opening));
+    }
+
+    // Implicit participants are valid PlantUML, only enforce declarations once the diagram uses some
+    if declared.is_empty() {
+        return;
+    }
+
+    let mut reported = HashSet::new();
+
+    for (line_index, name) in messages {
+        if !declared.contains(&name) && reported.insert(name.clone()) {
+            linter.report(LintRule::UndeclaredParticipant, line_index, format!("\"{name}\" is used without being declared"));
+        }
+    }
+}
+
+fn lint_include(linter: &mut Linter, line_index: usize, include_path: &str, defines: &[(usize, String, String)], file_path: &Path) {
+    let mut include_path = include_path.to_string();
+
+    for (_, name, value) in defines {
+        if include_path.starts_with(name.as_str()) {
+            include_path = include_path.replacen(name.as_str(), value, 1);
+        }
+    }
+
+    if let Some(resolved_path) = resolve_include(file_path, &include_path)
+        && !resolved_path.exists() {
+        linter.report(LintRule::MissingInclude, line_index, format!("\"{}\" does not exist", resolved_path.display()));
+    }
+}
+
+fn lint_declaration(linter: &mut Linter, declared: &mut HashSet<String>, aliases: &mut HashMap<String, usize>, line_index: usize, trimmed: &str) {
+    let trimmed = trimmed.strip_prefix("create ").unwrap_or(trimmed);
+    let tokens = split_tokens(trimmed);
+
+    let Some(keyword) = tokens.first() else {
+        return;
+    };
+
+    if !DECLARATION_KEYWORDS.contains(&keyword.to_lowercase().as_str()) || tokens.len() < 2 {
+        return;
+    }
+
+    declared.insert(unquote(tokens[1]));
+
+    if let Some(as_index) = tokens.iter().position(|token| *token == "as")
+        && let Some(alias) = tokens.get(as_index + 1) {
+        let alias = unquote(alias);
+
+        if let Some(first_line_index) = aliases.get(&alias) {
+            linter.report(LintRule::DuplicateAlias, line_index, format!("\"{alias}\" is already defined line {}", first_line_index + 1));
+        }
+        else {
+            aliases.insert(alias.clone(), line_index);
+        }
+
+        declared.insert(alias);
+    }
+}
+
+/// Participants on both sides of a message arrow, e.g. `Alice` and `Bob` in `Alice -> Bob ++ : Hello`.
+/// The sides outside of the diagram (e.g. `[->`) or not naming a participant are left out.
+fn message_participants(trimmed: &str) -> Option<(Option<String>, Option<String>)> {
+    if trimmed.starts_with([':', '=', '|', '.']) || trimmed.starts_with("skinparam") {
+        return None;
+    }
+
+    let head = message_head(trimmed);
+    let (start, end) = find_message_arrow(head)?;
+
+    let before = split_tokens(&head[..start]);
+    let after = split_tokens(&head[end..]);
+
+    let participant = |token: Option<&&str>| token
+        .filter(|token| !token.starts_with(['[', ']', '(', ':', '?', '<', '&']) && !token.ends_with(['[', ']']))
+        .map(|token| unquote(token));
+
+    let left = participant(before.last());
+    let right = participant(after.iter().find(|token| !ACTIVATION_MARKERS.contains(token) && !token.starts_with('#')));
+
+    Some((left, right))
+}
+
+/// Part of a message before its label, i.e. before the first `:` outside quotes
+fn message_head(trimmed: &str) -> &str {
+    let mut in_quotes = false;
+
+    for (index, char) in trimmed.char_indices() {
+        match char {
+            '"' => in_quotes = !in_quotes,
+            ':' if !in_quotes => return &trimmed[..index],
+            _ => {}
+        }
+    }
+
+    trimmed
+}
+
+/// Byte range of the first sequence arrow of the text, e.g. `->`, `<<--`, `-[#red]>>` or `->x`
+fn find_message_arrow(head: &str) -> Option<(usize, usize)> {
+    let chars = head.char_indices().collect::<Vec<_>>();
+    let mut in_quotes = false;
+    let mut index = 0;
+
+    while index < chars.len() {
+        let (start, char) = chars[index];
+
+        if char == '"' {
+            in_quotes = !in_quotes;
+        }
+
+        if in_quotes || !matches!(char, '-' | '.' | '<') {
+            index += 1;
+            continue;
+        }
+
+        let mut end_index = index;
+        let mut in_brackets = false;
+
+        while let Some((_, char)) = chars.get(end_index) {
+            match char {
+                '[' if end_index > index => in_brackets = true,
+                ']' if in_brackets => in_brackets = false,
+                _ if in_brackets => {},
+                '-' | '.' | '<' | '>' | '\\' | '/' | '*' => {},
+                _ => break
+            }
+
+            end_index += 1;
+        }
+
+        let arrow = chars[index..end_index].iter().map(|(_, char)| *char).collect::<String>();
+        let has_body = arrow.contains(['-', '.']);
+        let has_head = arrow.contains(['<', '>', '\\', '/']) || chars.get(end_index).is_some_and(|(_, char)| matches!(char, 'o' | 'x'));
+
+        if has_body && has_head {
+            // A letter head is only part of the arrow when it is followed by a space, e.g. `->x Bob`
+            let end_index = match chars.get(end_index) {
+                Some((_, 'o' | 'x')) if chars.get(end_index + 1).is_none_or(|(_, char)| char.is_whitespace()) => end_index + 1,
+                _ => end_index
+            };
+
+            return Some((start, chars.get(end_index).map(|(index, _)| *index).unwrap_or(head.len())));
+        }
+
+        index = end_index.max(index + 1);
+    }
+
+    None
+}
+
+/// Splits on whitespace, keeping quoted strings as a single token
+fn split_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut in_quotes = false;
+    let mut token_start = None;
+
+    for (index, char) in text.char_indices().chain([(text.len(), ' ')]) {
+        if char == '"' {
+            in_quotes = !in_quotes;
+        }
+
+        if char.is_whitespace() && !in_quotes {
+            if let Some(start) = token_start.take() {
+                tokens.push(&text[start..index]);
+            }
+        }
+        else if token_start.is_none() {
+            token_start = Some(index);
+        }
+    }
+
+    tokens
+}
+
+fn unquote(token: &str) -> String {
+    token.trim_matches('"').to_string()
+}
+
+fn contains_word(line: &str, word: &str) -> bool {
+    let is_word_char = |char: char| char.is_alphanumeric() || char == '_';
+
+    line.match_indices(word).any(|(index, _)| {
+        let before = line[..index].chars().next_back();
+        let after = line[index + word.len()..].chars().next();
+
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(text: &str) -> Vec<(LintRule, usize)> {
+        let file_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("diagram.puml");
+
+        lint_plantuml(text, &file_path, &LintConfig::default())
+            .into_iter()
+            .map(|diagnostic| (diagnostic.rule, diagnostic.line))
+            .collect()
+    }
+
+    #[test]
+    fn reports_undeclared_participants() {
+        assert_eq!(lint("@startuml\nparticipant Alice\nAlice -> Bob: Hello\nBob -> Alice\n@enduml"), [(LintRule::UndeclaredParticipant, 3)]);
+        assert_eq!(lint("@startuml\nAlice -> Bob: Hello\n@enduml"), []);
+        assert_eq!(lint("@startuml\nactor \"Big Alice\" as A\n[-> A\nA ->x A ++ : Hello\n@enduml"), []);
+    }
+
+    #[test]
+    fn reports_duplicate_aliases() {
+        assert_eq!(lint("@startuml\nparticipant Alice as A\nparticipant Bob as A\n@enduml"), [(LintRule::DuplicateAlias, 3)]);
+    }
+
+    #[test]
+    fn reports_unmatched_sequence_blocks() {
+        assert_eq!(lint("@startuml\nalt ok\nA -> B\nelse ko\nloop\nB -> A\nend\n@enduml"), [(LintRule::UnmatchedBlock, 2)]);
+        assert_eq!(lint("@startuml\nA -> B\nend\nelse\n@enduml"), [(LintRule::UnmatchedBlock, 3), (LintRule::UnmatchedBlock, 4)]);
+        assert_eq!(lint("@startuml\nbox Internal\nparticipant A\nend\n@enduml"), [(LintRule::UnmatchedBlock, 4)]);
+        assert_eq!(lint("@startuml\nnote left\nend of the text\nalt\nend note\n@enduml"), []);
+    }
+
+    #[test]
+    fn reports_unmatched_activity_blocks() {
+        assert_eq!(lint("@startuml\nrepeat\n:read;\nif (eof?) then (yes)\nbreak\nendif\n:process;\nrepeat while (more?)\nend\n@enduml"), []);
+        assert_eq!(lint("@startuml\nstart\nfork\n:a;\nfork again\n:b;\nend merge\nwhile (more?)\n:c;\nendif\n@enduml"), [(LintRule::UnmatchedBlock, 10)]);
+        assert_eq!(lint("@startuml\nstart\nif (x) then\n:a;\nstop\n@enduml"), [(LintRule::UnmatchedBlock, 3)]);
+    }
+
+    #[test]
+    fn reports_unmatched_braces() {
+        assert_eq!(lint("@startuml\nclass A {\n+name\n}\n}\n@enduml"), [(LintRule::UnmatchedBlock, 5)]);
+        assert_eq!(lint("@startjson\n{\n\"a\": [1, 2\n}\n@endjson"), []);
+    }
+
+    #[test]
+    fn reports_missing_includes() {
+        assert_eq!(lint("@startuml\n!include Cargo.toml\n!include missing.iuml\n!include <C4/C4_Container>\n@enduml"), [(LintRule::MissingInclude, 3)]);
+        assert_eq!(lint("!define DIR src\n@startuml\n!include DIR/main.rs\n@enduml"), []);
+    }
+
+    #[test]
+    fn reports_unused_defines() {
+        assert_eq!(lint("@startuml\n!define RED #FF0000\n!define BLUE #0000FF\nA -> B RED\n@enduml"), [(LintRule::UnusedDefine, 3)]);
+    }
+
+    #[test]
+    fn reports_missing_ends() {
+        assert_eq!(lint("@startuml\nA -> B\n"), [(LintRule::MissingEnd, 1)]);
+        assert_eq!(lint("@startuml\nA -> B\n@startuml\nB -> A\n@enduml"), [(LintRule::MissingEnd, 1)]);
+        assert_eq!(lint("@startuml\nA -> B\n@endmindmap"), [(LintRule::MissingEnd, 3)]);
+    }
+
+    #[test]
+    fn leaves_out_the_rules_turned_off() {
+        let config = toml::from_str::<LintConfig>("missing-end = \"off\"\nunused-define = \"error\"").unwrap();
+        let diagnostics = lint_plantuml("!define RED #FF0000\n@startuml\nA -> B\n", Path::new("diagram.puml"), &config);
+
+        assert_eq!(diagnostics.iter().map(|diagnostic| (diagnostic.rule, diagnostic.severity)).collect::<Vec<_>>(), [(LintRule::UnusedDefine, Severity::Error)]);
+    }
+}
@@ -1,2 +1,19 @@
-mod area_separation;
-mod clipboard;
\ No newline at end of file
+pub mod area_separation;
+pub mod batch;
+mod blocks;
+mod buffers;
+pub mod check;
+mod clipboard;
+mod dialog;
+mod embedded;
+mod expanded_source;
+pub mod format;
+mod include;
+pub mod lint;
+mod save;
+mod scratchpad;
+mod session;
+mod sidebar;
+mod swap;
+mod theme;
+pub mod zoom;
\ No newline at end of file
@@ -0,0 +1,74 @@
+use crate::app::{App, Dialog, PendingAction};
+use crokey::{key, KeyCombination};
+
+impl App<'_> {
+    /// Quits, asking first what to do with the unsaved text of the buffers. Quitting again discards it.
+    pub fn quit(&mut self) {
+        let is_dirty = self.buffers().any(|buffer| buffer.is_dirty());
+
+        match (is_dirty, &self.dialog) {
+            (true, Some(Dialog::UnsavedChanges { .. })) | (false, _) => self.should_quit = true,
+            (true, _) => self.dialog = Some(Dialog::UnsavedChanges { pending_action: PendingAction::Quit })
+        }
+    }
+
+    pub fn handle_unsaved_changes_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        let save = match key_combination {
+            key!(s) | key!(y) => true,
+            key!(d) | key!(n) => false,
+            key!(esc) => {
+                self.dialog = None;
+                return Ok(false);
+            },
+            _ => return Ok(false)
+        };
+
+        let Some(Dialog::UnsavedChanges { pending_action }) = self.dialog.take() else {
+            return Ok(false);
+        };
+
+        match pending_action {
+            PendingAction::Quit => {
+                // Quitting is dropped rather than losing the text, the buffer which could not be saved being shown
+                if save && let Some(should_render) = self.save_dirty_buffers()? {
+                    return Ok(should_render);
+                }
+
+                self.should_quit = true;
+                Ok(false)
+            },
+            PendingAction::Close => {
+                if save && !self.save_pmu_file() {
+                    return Ok(false);
+                }
+
+                self.close_buffer()
+            },
+            PendingAction::SwitchEmbeddedDiagram(block_index) => {
+                if save && !self.save_pmu_file() {
+                    return Ok(false);
+                }
+
+                Ok(self.switch_embedded_diagram(block_index))
+            }
+        }
+    }
+
+    /// Saves every buffer with unsaved text, stopping at the first failure, which is then shown.
+    /// Returns whether the diagram must be rendered after a failure, none meaning that all were saved.
+    fn save_dirty_buffers(&mut self) -> anyhow::Result<Option<bool>> {
+        for buffer_index in 0..=self.other_buffers.len() {
+            if !self.buffers().nth(buffer_index).is_some_and(|buffer| buffer.is_dirty()) {
+                continue;
+            }
+
+            let should_render = self.switch_buffer(buffer_index)?;
+
+            if !self.save_pmu_file() {
+                return Ok(Some(should_render));
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,177 @@
+use crate::app::{App, Dialog};
+use crate::files::atomic_write::write_atomically;
+use crate::files::scratchpad::{delete_scratchpad, is_scratchpad, list_scratchpads, new_scratchpad_path};
+use crate::files::session::{forget_file_session, save_file_session};
+use crate::files::utils::expand_tilde;
+use anyhow::bail;
+use crokey::crossterm::event::{KeyCode, KeyModifiers};
+use crokey::OneToThree::One;
+use crokey::{key, KeyCombination};
+use std::path::Path;
+use std::{env, path};
+
+impl App<'_> {
+    pub fn open_scratchpads_dialog(&mut self) {
+        let scratchpads = list_scratchpads(&self.data_dir);
+        let selected = scratchpads
+            .iter()
+            .position(|scratchpad| scratchpad.path == self.buffer.input_file_path)
+            .unwrap_or(0);
+
+        self.dialog = Some(Dialog::Scratchpads { scratchpads, selected });
+    }
+
+    /// Opens a new scratchpad, written at once so that it is listed with the others
+    pub fn new_scratchpad(&mut self) -> anyhow::Result<bool> {
+        let should_render = self.open_buffer(new_scratchpad_path(&self.data_dir))?;
+        self.autosave_pmu_file();
+
+        Ok(should_render)
+    }
+
+    /// Suggests the working directory for a scratchpad, the current path for a file
+    pub fn open_save_as_dialog(&mut self) {
+        let path = match is_scratchpad(&self.data_dir, &self.buffer.input_file_path) {
+            true => env::current_dir()
+                .unwrap_or_default()
+                .join(self.buffer.input_file_path.file_name().unwrap()),
+            false => self.buffer.input_file_path.clone()
+        };
+
+        self.dialog = Some(Dialog::SaveAs {
+            path: path.to_string_lossy().to_string(),
+            error: None,
+        });
+    }
+
+    pub fn handle_scratchpads_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        let Some(Dialog::Scratchpads { scratchpads, selected }) = self.dialog.as_mut() else {
+            return Ok(false);
+        };
+
+        match key_combination {
+            key!(esc) => self.dialog = None,
+            key!(up) => *selected = selected.saturating_sub(1),
+            key!(down) => *selected = (*selected + 1).min(scratchpads.len().saturating_sub(1)),
+            key!(n) => {
+                self.dialog = None;
+                return self.new_scratchpad();
+            },
+            key!(enter) => {
+                if let Some(scratchpad) = scratchpads.get(*selected) {
+                    let scratchpad_path = scratchpad.path.clone();
+
+                    self.dialog = None;
+                    return self.open_buffer(scratchpad_path);
+                }
+            },
+            key!(delete) => {
+                if let Some(scratchpad) = scratchpads.get(*selected) {
+                    let scratchpad_path = scratchpad.path.clone();
+                    let should_render = self.delete_scratchpad(&scratchpad_path)?;
+
+                    self.open_scratchpads_dialog();
+                    return Ok(should_render);
+                }
+            },
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    pub fn handle_save_as_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        let Some(Dialog::SaveAs { path, error }) = self.dialog.as_mut() else {
+            return Ok(false);
+        };
+
+        match key_combination {
+            key!(esc) => self.dialog = None,
+            key!(backspace) => {
+                path.pop();
+            },
+            key!(enter) => {
+                let new_path = path.clone();
+
+                match self.save_as(&new_path) {
+                    Ok(()) => {
+                        self.dialog = None;
+                        return Ok(true);
+                    },
+                    Err(err) => {
+                        if let Some(Dialog::SaveAs { error, .. }) = self.dialog.as_mut() {
+                            *error = Some(err.to_string());
+                        }
+                    }
+                }
+            },
+            KeyCombination { codes: One(KeyCode::Char(char)), modifiers } if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                path.push(char);
+                *error = None;
+            },
+            _ => {}
+        }
+
+        Ok(false)
+    }
+
+    /// Deletes a scratchpad and closes its buffer, another scratchpad being opened when it is the last buffer
+    fn delete_scratchpad(&mut self, scratchpad_path: &Path) -> anyhow::Result<bool> {
+        let is_current = scratchpad_path == self.buffer.input_file_path;
+
+        match (is_current, self.other_buffers.is_empty()) {
+            (true, false) => {
+                self.close_buffer()?;
+            },
+            (true, true) => {
+                let other_scratchpad_path = list_scratchpads(&self.data_dir)
+                    .into_iter()
+                    .map(|scratchpad| scratchpad.path)
+                    .find(|path| path != scratchpad_path)
+                    .unwrap_or_else(|| new_scratchpad_path(&self.data_dir));
+
+                self.open_pmu_file(other_scratchpad_path)?;
+                self.autosave_pmu_file();
+            },
+            (false, _) => self.forget_buffer(scratchpad_path)
+        }
+
+        delete_scratchpad(scratchpad_path)?;
+        forget_file_session(&self.data_dir, scratchpad_path)?;
+
+        Ok(is_current)
+    }
+
+    /// Writes the diagram to a new path and edits it there, a scratchpad being moved rather than copied
+    fn save_as(&mut self, new_path: &str) -> anyhow::Result<()> {
+        if new_path.trim().is_empty() {
+            bail!("The path is empty");
+        }
+
+        let new_path = path::absolute(expand_tilde(Path::new(new_path.trim())))?;
+
+        if new_path.exists() {
+            bail!("\"{}\" already exists", new_path.display());
+        }
+
+        if !new_path.parent().is_some_and(Path::is_dir) {
+            bail!("The directory of \"{}\" does not exist", new_path.display());
+        }
+
+        write_atomically(&new_path, self.buffer.text_input.text.as_bytes())?;
+
+        // The new file starts where the current one was left
+        let previous_path = self.buffer.input_file_path.clone();
+        self.save_session()?;
+        save_file_session(&self.data_dir, &new_path, &self.buffer.file_session)?;
+
+        self.open_pmu_file(new_path)?;
+
+        if is_scratchpad(&self.data_dir, &previous_path) {
+            delete_scratchpad(&previous_path)?;
+            forget_file_session(&self.data_dir, &previous_path)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use crate::app::App;
+use crate::files::session::save_file_session;
+
+impl App<'_> {
+    /// Remembers where the file was left, to restore it on the next open
+    pub fn save_session(&mut self) -> anyhow::Result<()> {
+        self.buffer.file_session.cursor_position = self.buffer.text_input.cursor_position;
+        self.buffer.file_session.scroll_offset = self.buffer.text_input.scroll_offset;
+        self.buffer.file_session.split = Some(self.editor_percentage);
+        self.buffer.file_session.dark_mode = Some(self.dark_mode);
+        self.buffer.file_session.zoom = Some(self.zoom);
+        self.buffer.file_session.embedded_diagram = self.buffer.embedded.as_ref().map(|document| document.block_index);
+
+        if let Some(render_status) = self.buffer.render_status() {
+            self.buffer.file_session.render_status = Some(render_status);
+        }
+
+        save_file_session(&self.data_dir, &self.buffer.input_file_path, &self.buffer.file_session)
+    }
+
+    /// Saves the session of every buffer on exit, the hidden ones having rendered since they were left
+    pub fn save_sessions(&mut self) -> anyhow::Result<()> {
+        self.save_session()?;
+
+        for buffer in self.other_buffers.iter_mut() {
+            if let Some(render_status) = buffer.render_status() {
+                buffer.file_session.render_status = Some(render_status);
+            }
+
+            save_file_session(&self.data_dir, &buffer.input_file_path, &buffer.file_session)?;
+        }
+
+        Ok(())
+    }
+}
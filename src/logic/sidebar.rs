@@ -0,0 +1,125 @@
+use crate::app::{App, Sidebar};
+use crate::files::diagram_dir::list_diagram_files;
+use crate::files::scratchpad::is_scratchpad;
+use crate::files::session::load_file_sessions;
+use crokey::crossterm::event::{KeyCode, KeyModifiers};
+use crokey::OneToThree::One;
+use crokey::{key, KeyCombination};
+use std::env;
+use std::path::{Path, PathBuf};
+
+impl App<'_> {
+    /// Lists the diagrams of the directory in a focused sidebar
+    pub fn open_sidebar(&mut self, dir_path: PathBuf) {
+        let render_statuses = load_file_sessions(&self.data_dir)
+            .into_iter()
+            .filter_map(|(input_file_path, file_session)| Some((input_file_path, file_session.render_status?)))
+            .collect();
+
+        self.sidebar = Some(Sidebar {
+            file_paths: list_diagram_files(&dir_path),
+            dir_path,
+            render_statuses,
+            filter: String::new(),
+            selected: 0,
+            focused: true,
+        });
+    }
+
+    /// Focuses the sidebar, listing the directory of the current file when hidden, and hides it when focused
+    pub fn toggle_sidebar(&mut self) {
+        match &mut self.sidebar {
+            Some(sidebar) if sidebar.focused => self.sidebar = None,
+            Some(sidebar) => sidebar.focused = true,
+            None => {
+                let dir_path = match is_scratchpad(&self.data_dir, &self.buffer.input_file_path) {
+                    true => env::current_dir().unwrap_or_default(),
+                    false => self.buffer.input_file_path.parent().map(Path::to_path_buf).unwrap_or_default()
+                };
+
+                self.open_sidebar(dir_path);
+            }
+        }
+    }
+
+    pub fn handle_sidebar_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        let Some(sidebar) = self.sidebar.as_mut() else {
+            return Ok(false);
+        };
+
+        match key_combination {
+            key!(esc) => sidebar.focused = false,
+            key!(up) => sidebar.selected = sidebar.selected.saturating_sub(1),
+            key!(down) => sidebar.selected = (sidebar.selected + 1).min(sidebar.filtered_file_paths().len().saturating_sub(1)),
+            key!(backspace) => {
+                sidebar.filter.pop();
+                sidebar.selected = 0;
+            },
+            key!(enter) => {
+                if let Some(input_file_path) = sidebar.filtered_file_paths().get(sidebar.selected).map(|file_path| file_path.to_path_buf()) {
+                    sidebar.focused = false;
+                    return self.open_buffer(input_file_path);
+                }
+            },
+            KeyCombination { codes: One(KeyCode::Char(char)), modifiers } if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                sidebar.filter.push(char);
+                sidebar.selected = 0;
+            },
+            _ => {}
+        }
+
+        Ok(false)
+    }
+}
+
+impl Sidebar {
+    /// Path of the file relative to the listed directory
+    pub fn relative_path(&self, file_path: &Path) -> String {
+        file_path.strip_prefix(&self.dir_path).unwrap_or(file_path).to_string_lossy().to_string()
+    }
+
+    /// Files matching the filter, the best matches first
+    pub fn filtered_file_paths(&self) -> Vec<&Path> {
+        if self.filter.is_empty() {
+            return self.file_paths.iter().map(PathBuf::as_path).collect();
+        }
+
+        let mut scored_file_paths = self.file_paths
+            .iter()
+            .filter_map(|file_path| Some((fuzzy_score(&self.filter, &self.relative_path(file_path))?, file_path.as_path())))
+            .collect::<Vec<_>>();
+
+        scored_file_paths.sort_by(|(score, file_path), (other_score, other_file_path)| other_score.cmp(score).then_with(|| file_path.cmp(other_file_path)));
+        scored_file_paths.into_iter().map(|(_, file_path)| file_path).collect()
+    }
+}
+
+/// Matches the query letters in order, ignoring case, and scores the consecutive letters and the ones starting a name or a word higher.
+/// None when the candidate does not hold every letter.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate = candidate.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut candidate_index = 0;
+    let mut previous_match_index = None;
+
+    for query_char in query.chars().filter(|char| !char.is_whitespace()) {
+        let match_index = (candidate_index..candidate.len())
+            .find(|index| candidate[*index].to_lowercase().eq(query_char.to_lowercase()))?;
+
+        score += 1;
+
+        if previous_match_index.is_some_and(|previous_match_index| previous_match_index + 1 == match_index) {
+            score += 5;
+        }
+
+        if match_index == 0 || matches!(candidate[match_index - 1], '/' | '\\' | '-' | '_' | '.' | ' ') {
+            score += 3;
+        }
+
+        previous_match_index = Some(match_index);
+        candidate_index = match_index + 1;
+    }
+
+    // Shorter paths win among equal matches
+    Some(score * 100 - candidate.len() as i32)
+}
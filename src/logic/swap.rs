@@ -0,0 +1,112 @@
+use crate::app::{App, Buffer, Dialog};
+use crate::files::swap::{diff_lines, find_orphaned_swap_file, remove_swap_file, write_swap_file};
+use crokey::{key, KeyCombination};
+use std::iter;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+impl App<'_> {
+    /// Backs up the unsaved text of every buffer, the hidden ones included
+    pub fn update_swap_files(&mut self) {
+        for buffer in iter::once(&mut self.buffer).chain(self.other_buffers.iter_mut()) {
+            buffer.update_swap_file(&self.data_dir);
+        }
+    }
+
+    pub fn close_swap_file(&mut self) {
+        self.buffer.close_swap_file(&self.data_dir);
+    }
+
+    pub fn close_swap_files(&mut self) {
+        for buffer in iter::once(&mut self.buffer).chain(self.other_buffers.iter_mut()) {
+            buffer.close_swap_file(&self.data_dir);
+        }
+    }
+
+    /// Offers to recover the text of a crashed process editing the same file
+    pub fn check_swap_file(&mut self) {
+        if let Some(swap_file) = find_orphaned_swap_file(&self.data_dir, &self.buffer.input_file_path, &self.buffer.saved_text) {
+            self.dialog = Some(Dialog::Recover {
+                swap_file,
+                diff: None,
+                scroll: 0,
+            });
+        }
+    }
+
+    pub fn handle_recover_event(&mut self, key_combination: KeyCombination) -> anyhow::Result<bool> {
+        let Some(Dialog::Recover { swap_file, diff, scroll }) = self.dialog.as_mut() else {
+            return Ok(false);
+        };
+
+        match key_combination {
+            key!(r) => {
+                let cursor_position = self.buffer.text_input.cursor_position;
+
+                self.buffer.text_input.text = swap_file.text.clone();
+                self.buffer.text_input.set_cursor_position(cursor_position);
+                self.dialog = None;
+
+                // The recovered text replaces the orphaned swap file right away
+                self.buffer.swap_outdated = true;
+                self.buffer.swap_written_at = None;
+                return Ok(true);
+            },
+            key!(x) => {
+                remove_swap_file(&self.data_dir, &self.buffer.input_file_path);
+                self.dialog = None;
+            },
+            key!(d) => {
+                *diff = match diff {
+                    Some(_) => None,
+                    None => Some(diff_lines(&self.buffer.saved_text, &swap_file.text))
+                };
+                *scroll = 0;
+            },
+            key!(up) => *scroll = scroll.saturating_sub(1),
+            key!(down) => {
+                let line_count = diff.as_ref().map_or(0, Vec::len) as u16;
+                *scroll = (*scroll + 1).min(line_count.saturating_sub(1));
+            },
+            _ => {}
+        }
+
+        Ok(false)
+    }
+}
+
+impl Buffer<'_> {
+    /// Writes the unsaved text to the swap file at most once per interval, and removes it once the text is saved
+    fn update_swap_file(&mut self, data_dir: &Path) {
+        let interval = Duration::from_millis(self.project_config.config.editor.swap_interval_ms);
+
+        if interval.is_zero() {
+            return;
+        }
+
+        if !self.is_dirty() {
+            self.close_swap_file(data_dir);
+            return;
+        }
+
+        let is_due = self.swap_written_at.is_none_or(|swap_written_at| swap_written_at.elapsed() >= interval);
+
+        if self.swap_outdated && is_due {
+            if let Err(err) = write_swap_file(data_dir, &self.input_file_path, &self.text_input.text) {
+                self.save_error = Some(format!("Could not write the swap file: {err:#}"));
+            }
+
+            self.swap_written_at = Some(Instant::now());
+            self.swap_outdated = false;
+        }
+    }
+
+    /// Removes the swap file written by this process, the text being saved or discarded
+    fn close_swap_file(&mut self, data_dir: &Path) {
+        if self.swap_written_at.take().is_some() {
+            remove_swap_file(data_dir, &self.input_file_path);
+        }
+
+        self.swap_outdated = false;
+    }
+}
@@ -0,0 +1,48 @@
+use crate::app::App;
+use crate::files::session::save_file_session;
+
+/// Themes bundled with PlantUML, usable with `-theme` without any network access
+pub const PLANTUML_THEMES: [&str; 44] = [
+    "amiga", "aws-orange", "black-knight", "bluegray", "blueprint", "carbon-gray", "cerulean", "cerulean-outline",
+    "cloudscape-design", "crt-amber", "crt-green", "cyborg", "cyborg-outline", "hacker", "lightgray", "mars",
+    "materia", "materia-outline", "metal", "mimeograph", "minty", "mono", "plain", "reddress-darkblue",
+    "reddress-darkgreen", "reddress-darkorange", "reddress-darkred", "reddress-lightblue", "reddress-lightgreen", "reddress-lightorange", "reddress-lightred", "sandstone",
+    "silver", "sketchy", "sketchy-outline", "spacelab", "spacelab-white", "sunlust", "superhero", "superhero-outline",
+    "toy", "united", "vibrant", "_none_",
+];
+
+impl App<'_> {
+    /// Switches the preview to the next built-in theme, going back to the configured one after the last
+    pub fn next_theme(&mut self) -> anyhow::Result<()> {
+        let next_index = match self.theme_index() {
+            None => Some(0),
+            Some(index) if index + 1 < PLANTUML_THEMES.len() => Some(index + 1),
+            Some(_) => None
+        };
+
+        self.set_theme(next_index)
+    }
+
+    pub fn previous_theme(&mut self) -> anyhow::Result<()> {
+        let previous_index = match self.theme_index() {
+            None => Some(PLANTUML_THEMES.len() - 1),
+            Some(0) => None,
+            Some(index) => Some(index - 1)
+        };
+
+        self.set_theme(previous_index)
+    }
+
+    fn theme_index(&self) -> Option<usize> {
+        self.buffer.file_session.theme
+            .as_ref()
+            .and_then(|theme| PLANTUML_THEMES.iter().position(|other| other == theme))
+    }
+
+    /// Changes the theme injected with `-theme`, leaving the source untouched, and remembers it for the file
+    fn set_theme(&mut self, theme_index: Option<usize>) -> anyhow::Result<()> {
+        self.buffer.file_session.theme = theme_index.map(|index| PLANTUML_THEMES[index].to_string());
+
+        save_file_session(&self.data_dir, &self.buffer.input_file_path, &self.buffer.file_session)
+    }
+}
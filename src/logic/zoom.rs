@@ -0,0 +1,43 @@
+use crate::app::App;
+use image::DynamicImage;
+
+/// Zoom of a preview fitted to its pane, in percent
+pub const DEFAULT_ZOOM: u16 = 100;
+const MAX_ZOOM: u16 = 400;
+const ZOOM_STEP: u16 = 25;
+
+impl App<'_> {
+    pub fn zoom_in(&mut self) {
+        self.zoom = clamp_zoom(self.zoom.saturating_add(ZOOM_STEP));
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = clamp_zoom(self.zoom.saturating_sub(ZOOM_STEP));
+    }
+}
+
+pub fn clamp_zoom(zoom: u16) -> u16 {
+    zoom.clamp(DEFAULT_ZOOM, MAX_ZOOM)
+}
+
+/// Center of the image, enlarged by the zoom once fitted to the preview
+pub fn zoomed_image(image: &DynamicImage, zoom: u16) -> DynamicImage {
+    let zoomed_length = |length: u32| (u64::from(length) * u64::from(DEFAULT_ZOOM) / u64::from(zoom.max(DEFAULT_ZOOM))).max(1) as u32;
+    let (width, height) = (zoomed_length(image.width()), zoomed_length(image.height()));
+
+    image.crop_imm((image.width() - width) / 2, (image.height() - height) / 2, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crops_the_center_of_the_zoomed_image() {
+        let image = DynamicImage::new_rgb8(400, 200);
+
+        assert_eq!((zoomed_image(&image, 200).width(), zoomed_image(&image, 200).height()), (200, 100));
+        assert_eq!(zoomed_image(&image, DEFAULT_ZOOM).width(), 400);
+        assert_eq!(clamp_zoom(1000), MAX_ZOOM);
+    }
+}
@@ -1,6 +1,12 @@
 use crate::app::App;
 use ratatui::{init, restore};
-use crate::args::ARGS;
+use crate::args::{validate_args, ARGS};
+use crate::error::error_exit_code;
+use crate::files::config::validate_config;
+use crokey::crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crokey::crossterm::execute;
+use std::{io, panic};
+use std::process::ExitCode;
 
 mod app;
 mod ui;
@@ -11,23 +17,55 @@ mod render;
 mod widgets;
 mod logic;
 mod cli;
+mod error;
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:#}");
+            ExitCode::from(error_exit_code(&err))
+        }
+    }
+}
+
+async fn run() -> anyhow::Result<()> {
+    validate_args()?;
+    validate_config()?;
+
     let mut app = App::new()?;
 
-    
     if let Some(command) = &ARGS.command {
         app.handle_command(command).await?;
     }
     else {
         let terminal = init();
+        // The divider between the editor and the preview is dragged with the mouse
+        let mouse = app.buffer.project_config.config.ui.mouse;
 
-        app
+        if mouse {
+            execute!(io::stdout(), EnableMouseCapture)?;
+
+            // The terminal is given back its mouse on a panic too, before the hook restoring the terminal
+            let restore_hook = panic::take_hook();
+
+            panic::set_hook(Box::new(move |panic_info| {
+                execute!(io::stdout(), DisableMouseCapture).ok();
+                restore_hook(panic_info);
+            }));
+        }
+
+        let result = app
             .run(terminal)
-            .await?;
+            .await;
+
+        if mouse {
+            execute!(io::stdout(), DisableMouseCapture).ok();
+        }
 
         restore();
+        result?;
     }
     
     Ok(())
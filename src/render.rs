@@ -1,41 +1,169 @@
 use crate::app::{App, RenderOutput};
+use crate::args::ARGS;
+use crate::files::config::CONFIG;
+use crate::files::embedded::wrap_diagram_source;
+use crate::files::project_config::ProjectConfig;
+use crate::files::utils::fnv1a_hash;
 use async_process::Command;
-use image::ImageReader;
+use crate::error::CliError;
 use parking_lot::RwLock;
-use plantuml_parser::PlantUmlFileData;
 use ratatui_image::picker::Picker;
-use std::env;
-use std::path::PathBuf;
-use std::process::Output;
+use std::collections::BTreeMap;
+use std::path::{self, Path, PathBuf};
+use std::{fs, io};
+use std::process::{Output, Stdio};
 use std::sync::Arc;
 use std::time::Instant;
 use anyhow::anyhow;
+use futures_lite::{future, AsyncWriteExt};
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use tokio_util::sync::CancellationToken;
 
-const PLANTUML_COMMAND: Lazy<String> = Lazy::new(|| {
-    let plantuml_env = env::var("PLANT_UML");
-    let plantuml_command = match plantuml_env {
-        Ok(command) if !command.is_empty() => command,
-        _ => String::from("plantuml")
-    };
-    
-    plantuml_command.trim().to_string()
+/// Configured PlantUML command, overridden by the `PLANT_UML` env var
+pub static PLANTUML_COMMAND: Lazy<String> = Lazy::new(|| {
+    match CONFIG.renderer.command.trim().is_empty() {
+        true => String::from("plantuml"),
+        false => CONFIG.renderer.command.trim().to_string()
+    }
 });
 
+/// Options passed through to PlantUML, from the `[plantuml]` section of the project configuration and the command line
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct PlantUmlOptions {
+    /// Variables defined with `-DKEY=VALUE`
+    pub defines: BTreeMap<String, String>,
+    /// Configuration file given with `-config`
+    pub config: Option<PathBuf>,
+    /// Files included with `-I` as if `!include` was used
+    pub includes: Vec<PathBuf>,
+    pub theme: Option<String>,
+    pub charset: Option<String>,
+    /// Arguments appended as is
+    pub args: Vec<String>,
+}
+
+impl PlantUmlOptions {
+    /// Overrides these options with the given ones, lists being appended
+    pub fn merge(mut self, other: &PlantUmlOptions) -> PlantUmlOptions {
+        self.defines.extend(other.defines.clone());
+        self.includes.extend(other.includes.iter().cloned());
+        self.args.extend(other.args.iter().cloned());
+
+        if other.config.is_some() {
+            self.config = other.config.clone();
+        }
+
+        if other.theme.is_some() {
+            self.theme = other.theme.clone();
+        }
+
+        if other.charset.is_some() {
+            self.charset = other.charset.clone();
+        }
+
+        self
+    }
+
+    /// Makes the relative paths relative to the given directory instead of the working one
+    pub fn resolve_paths(&mut self, base_dir_path: &Path) {
+        if let Some(config) = self.config.as_mut() {
+            *config = base_dir_path.join(&config);
+        }
+
+        for include in self.includes.iter_mut() {
+            *include = base_dir_path.join(&include);
+        }
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![];
+
+        for (key, value) in &self.defines {
+            match value.is_empty() {
+                true => args.push(format!("-D{key}")),
+                false => args.push(format!("-D{key}={value}"))
+            }
+        }
+
+        if let Some(config) = &self.config {
+            args.push(String::from("-config"));
+            args.push(config.to_string_lossy().to_string());
+        }
+
+        for include in &self.includes {
+            args.push(format!("-I{}", include.display()));
+        }
+
+        if let Some(theme) = &self.theme {
+            args.push(String::from("-theme"));
+            args.push(theme.clone());
+        }
+
+        if let Some(charset) = &self.charset {
+            args.push(String::from("-charset"));
+            args.push(charset.clone());
+        }
+
+        args.extend(self.args.iter().cloned());
+        args
+    }
+}
+
+/// Mode of the renders run from the command line, light unless asked otherwise by a flag or the `[render]` configuration
+pub fn effective_plantuml_mode(project_config: &ProjectConfig) -> &'static str {
+    dark_mode_to_plantuml_mode(cli_dark_mode(ARGS.light_mode, ARGS.dark_mode, project_config.render.dark_mode))
+}
+
+/// The outputs don't follow the mode of the TUI, which is a display preference
+fn cli_dark_mode(light_mode: bool, dark_mode: bool, render_dark_mode: bool) -> bool {
+    match (light_mode, dark_mode) {
+        (false, false) => render_dark_mode,
+        (light_mode, dark_mode) => !light_mode || dark_mode
+    }
+}
+
+/// Options of the user configuration, overridden by the project ones, themselves overridden by the command line ones
+pub fn effective_plantuml_options(project_config: &ProjectConfig) -> PlantUmlOptions {
+    project_config.config.plantuml
+        .clone()
+        .merge(&ARGS.plantuml_options())
+}
+
 impl App<'_> {
     pub async fn render_plantuml(&mut self) -> anyhow::Result<()> {
-        if self.render_output.write().pending {
-            self.cancellation_token.cancel();
+        // The render of an outdated text is dropped, the new one getting its own token
+        if self.buffer.render_output.write().pending {
+            self.buffer.cancellation_token.cancel();
+            self.buffer.cancellation_token = CancellationToken::new();
+        }
+
+        self.watch_included_files();
+
+        if self.expanded_source.is_some() {
+            self.expand_source();
         }
 
-        let render_output_clone = self.render_output.clone();
+        let render_output_clone = self.buffer.render_output.clone();
         let data_dir = self.data_dir.clone();
-        let input_file_path = self.input_file_path.clone();
+        let input_file_path = self.buffer.input_file_path.clone();
         let picker = self.picker.clone();
-        let text_input = self.text_input.text.clone();
         let dark_mode = self.dark_mode;
-        let cancellation_token = self.cancellation_token.clone();
+
+        // The diagram of a document may leave out `@startuml`, the error lines being shifted back to the editor ones
+        let (text_input, line_offset) = match &self.buffer.embedded {
+            Some(_) => wrap_diagram_source(&self.buffer.text_input.text),
+            None => (self.buffer.text_input.text.clone(), 0)
+        };
+        let mut plantuml_options = effective_plantuml_options(&self.buffer.project_config);
+
+        // The theme picked in the preview wins over the configured one
+        if let Some(theme) = &self.buffer.file_session.theme {
+            plantuml_options.theme = Some(theme.clone());
+        }
+
+        let cancellation_token = self.buffer.cancellation_token.clone();
 
         tokio::spawn(async move {
             tokio::select! {
@@ -45,25 +173,30 @@ impl App<'_> {
                     input_file_path,
                     picker,
                     text_input,
-                    dark_mode
+                    line_offset,
+                    dark_mode,
+                    plantuml_options
                 ) => {},
                 _ = cancellation_token.cancelled() => {},
             }
         });
-        
-        self.cancellation_token = CancellationToken::new();
-        
+
         Ok(())
     }
 }
 
+/// Renders the text as it is in the editor, through stdin, so that the preview never waits for or races with the saves.
+/// Relative `!include`s are resolved from the directory of the edited file.
+#[allow(clippy::too_many_arguments)]
 async fn render_plantuml_task(
     render_output_clone: Arc<RwLock<RenderOutput>>,
     data_dir: PathBuf,
     input_file_path: PathBuf,
     picker: Option<Picker>,
     text_input: String,
-    dark_mode: bool
+    line_offset: usize,
+    dark_mode: bool,
+    plantuml_options: PlantUmlOptions
 ) {
     render_output_clone.write().pending = true;
 
@@ -73,82 +206,77 @@ async fn render_plantuml_task(
     };
 
     let mode = dark_mode_to_plantuml_mode(dark_mode);
+    let working_dir_path = input_file_path.parent().unwrap_or(&data_dir);
     
     let initial_time = Instant::now();
 
-    let render_command_output = render_command(output_format, mode, &data_dir, &input_file_path).await;
+    let render_command_output = render_pipe_command(output_format, mode, &plantuml_options, text_input.as_bytes(), working_dir_path).await;
     
     {
         let mut render_output = render_output_clone.write();
 
         render_output.time = initial_time.elapsed().as_millis().to_string();
+        render_output.dark_mode = dark_mode;
+        render_output.image = None;
+        render_output.source_image = None;
+        render_output.file_path = None;
 
         match render_command_output {
-            Ok(output) => {
-                if output.stderr.len() == 0 {
-                    let output_path = data_dir
-                        .join(input_file_path.file_stem().unwrap())
-                        .with_extension(extension);
-
-                    render_output.image = None;
+            Ok(output) if output.status.success() => {
+                // The output is kept in the data directory for the clipboard and the text preview,
+                // named after the whole path so that the tabs of files sharing a name don't overwrite each other's
+                let absolute_path = path::absolute(&input_file_path).unwrap_or_else(|_| input_file_path.clone());
+                let output_path = data_dir.join(format!(
+                    "{}-{:016x}.{}",
+                    input_file_path.file_stem().unwrap().to_string_lossy(),
+                    fnv1a_hash(absolute_path.as_os_str().as_encoded_bytes()),
+                    extension
+                ));
 
-                    if let Ok(dyn_img) = ImageReader::open(&output_path).expect("Could not open output file").decode() {
-                        if let Some(picker) = picker {
-                            render_output.image = Some(picker.new_resize_protocol(dyn_img));
+                match fs::write(&output_path, &output.stdout) {
+                    Ok(()) => {
+                        // The preview image is made when drawn, at the zoom of the app
+                        if picker.is_some()
+                            && let Ok(dyn_img) = image::load_from_memory(&output.stdout) {
+                            render_output.source_image = Some(dyn_img);
                         }
-                    }
-                    render_output.file_path = Some(output_path);
-                    render_output.render_error = None;
-                }
-                else {
-                    if let Err(parse_error) = PlantUmlFileData::parse_from_str(text_input) {
-                        let stringed_error: String = match parse_error {
-                            plantuml_parser::Error::Parse(err) => {
-                                let err = err.to_string();
-                                let comma_split = err.split(',').collect::<Vec<_>>();
-
-                                if comma_split.len() >= 4 {
-                                    let begin = comma_split[comma_split.len() - 3];
-                                    let end = comma_split[comma_split.len() - 2];
-                                    format!("Parse error\n{},{}", &begin[1..], &end[..end.len() - 3])
-                                }
-                                else {
-                                    err
-                                }
-                            },
-                            plantuml_parser::Error::PathResolver(err) => err.to_string(),
-                            plantuml_parser::Error::DiagramKindNotMatch(_, _) => "The diagram kind in the start keyword and the diagram kind in the end keyword are not match".to_string(),
-                            plantuml_parser::Error::ContentUnclosed(_) | plantuml_parser::Error::IsNotBlockComment => "An end keyword is not found in PlantUmlContent".to_string(),
-                            plantuml_parser::Error::Unreachable(err) => err
-                        };
-
-                        render_output.render_error = Some(stringed_error);
-                    }
-                    else {
-                        render_output.render_error = Some(String::from_utf8_lossy(&output.stderr).to_string());
-                    }
-
-                    render_output.image = None;
-                    render_output.file_path = None;
+
+                        render_output.file_path = Some(output_path);
+                        render_output.render_error = None;
+                    },
+                    Err(err) => render_output.render_error = Some(format!("Could not write \"{}\": {}", output_path.display(), err))
                 }
             },
-            Err(_) => render_output.file_path = None
+            Ok(output) => {
+                let (line, message) = parse_plantuml_error(&String::from_utf8_lossy(&output.stderr));
+
+                render_output.render_error = Some(match line {
+                    Some(line) => format!("{message}\nLine {}", line.saturating_sub(line_offset)),
+                    None => message
+                });
+            },
+            Err(err) => render_output.render_error = Some(match err.downcast_ref::<io::Error>() {
+                Some(io_error) if io_error.kind() == io::ErrorKind::NotFound => CliError::missing_renderer().to_string(),
+                _ => format!("{err:#}")
+            })
         }
 
         render_output.pending = false;
     }
 }
 
-pub async fn render_command(output_format: &str, mode: &str, output_dir_path: &PathBuf, input_file_path: &PathBuf) -> anyhow::Result<Output> {
-    match Command::new(&*PLANTUML_COMMAND)
-        .args(vec![
-            output_format,
-            "-nbthread", "auto",
-            mode,
-            "-failfast2",
+pub async fn render_command(output_format: &str, mode: &str, plantuml_options: &PlantUmlOptions, output_dir_path: &Path, input_file_path: &Path) -> anyhow::Result<Output> {
+    let args = plantuml_args(
+        [output_format, "-nbthread", "auto", mode, "-failfast2"],
+        plantuml_options,
+        [
             "-output", output_dir_path.as_os_str().to_str().unwrap(),
             input_file_path.as_os_str().to_str().unwrap(),
-        ])
+        ]
+    );
+
+    match plantuml_command()
+        .args(args)
         .output()
         .await {
         Ok(output) => Ok(output),
@@ -156,9 +284,155 @@ pub async fn render_command(output_format: &str, mode: &str, output_dir_path: &P
     }
 }
 
+/// Renders the diagram given on stdin with PlantUML's `-pipe` mode, the output being written on stdout.
+/// Relative `!include`s are resolved from the working directory.
+pub async fn render_pipe_command(output_format: &str, mode: &str, plantuml_options: &PlantUmlOptions, input: &[u8], working_dir_path: &Path) -> anyhow::Result<Output> {
+    let args = plantuml_args([output_format, mode, "-failfast2"], plantuml_options, ["-pipe"]);
+
+    run_plantuml_with_stdin(args, input, working_dir_path).await
+}
+
+/// The PlantUML command may hold arguments, e.g. `java -jar plantuml.jar`, quoted as in a shell (checked when loading the configuration)
+fn plantuml_command() -> Command {
+    let parts = shell_words::split(&PLANTUML_COMMAND).unwrap_or_else(|_| vec![PLANTUML_COMMAND.clone()]);
+    let mut parts = parts.iter();
+    let mut command = Command::new(parts.next().map(String::as_str).unwrap_or("plantuml"));
+
+    command.args(parts);
+    command
+}
+
+/// Puts the options between the leading and trailing arguments, leaving out the empty ones (e.g. the light mode)
+fn plantuml_args<const N: usize, const M: usize>(leading_args: [&str; N], plantuml_options: &PlantUmlOptions, trailing_args: [&str; M]) -> Vec<String> {
+    leading_args
+        .into_iter()
+        .map(String::from)
+        .chain(plantuml_options.to_args())
+        .chain(trailing_args.into_iter().map(String::from))
+        .filter(|arg| !arg.is_empty())
+        .collect()
+}
+
+/// Runs PlantUML's `-syntax` check on the diagram given on stdin, the diagram type or the error being written on stdout
+pub async fn syntax_command(plantuml_options: &PlantUmlOptions, input: &[u8], working_dir_path: &Path) -> anyhow::Result<Output> {
+    let args = plantuml_args([], plantuml_options, ["-syntax"]);
+
+    run_plantuml_with_stdin(args, input, working_dir_path).await
+}
+
+/// Runs PlantUML's preprocessor on the diagram given on stdin, the source with its includes, definitions and procedures expanded being written on stdout
+pub async fn preproc_command(plantuml_options: &PlantUmlOptions, input: &[u8], working_dir_path: &Path) -> anyhow::Result<Output> {
+    let args = plantuml_args(["-preproc"], plantuml_options, ["-pipe"]);
+
+    run_plantuml_with_stdin(args, input, working_dir_path).await
+}
+
+/// Runs PlantUML in the working directory with the input given on stdin, collecting its stdout and stderr
+async fn run_plantuml_with_stdin(args: Vec<String>, input: &[u8], working_dir_path: &Path) -> anyhow::Result<Output> {
+    let mut child = plantuml_command()
+        .args(args)
+        .current_dir(working_dir_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // PlantUML may start writing (or exit on an error) before it has read the whole input, so stdin is written while the output is read
+    let stdin = child.stdin.take();
+    let write_input = async move {
+        match stdin {
+            Some(mut stdin) => match stdin.write_all(input).await {
+                Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result
+            },
+            None => Ok(())
+        }
+    };
+    let (write_result, output_result) = future::zip(write_input, child.output()).await;
+
+    write_result?;
+    Ok(output_result?)
+}
+
+/// Extracts the line (1-based) and a readable message from a `plantuml_parser` error
+pub fn describe_parse_error(parse_error: &plantuml_parser::Error, text: &str) -> (Option<usize>, String) {
+    match parse_error {
+        // The error only exposes the position of the unparsed input through its debug representation
+        plantuml_parser::Error::Parse(err) => {
+            let line = err
+                .to_string()
+                .rsplit_once("begin: ")
+                .and_then(|(_, rest)| rest.split(|char: char| !char.is_ascii_digit()).next())
+                .and_then(|begin| begin.parse::<usize>().ok())
+                .map(|begin| text.as_bytes()[..begin.min(text.len())].iter().filter(|byte| **byte == b'\n').count() + 1);
+
+            (line, String::from("Parse error"))
+        },
+        plantuml_parser::Error::PathResolver(err) => (None, err.to_string()),
+        plantuml_parser::Error::DiagramKindNotMatch(_, _) => (None, String::from("The diagram kind in the start keyword and the diagram kind in the end keyword are not match")),
+        plantuml_parser::Error::ContentUnclosed(_) | plantuml_parser::Error::IsNotBlockComment => (None, String::from("An end keyword is not found in PlantUmlContent")),
+        plantuml_parser::Error::Unreachable(err) => (None, err.clone())
+    }
+}
+
+/// Extracts the line and the message from PlantUML error output.
+/// Handles both `Error line 3 in file: diagram.puml` and the `-pipe` mode `ERROR` followed by the line number.
+pub fn parse_plantuml_error(stderr: &str) -> (Option<usize>, String) {
+    let mut line = None;
+    let mut messages = vec![];
+    let mut previous_line_is_error = false;
+
+    for stderr_line in stderr.lines().map(str::trim).filter(|stderr_line| !stderr_line.is_empty()) {
+        let error_line = match previous_line_is_error {
+            true => stderr_line.parse::<usize>().ok(),
+            false => stderr_line
+                .strip_prefix("Error line ")
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|number| number.parse::<usize>().ok())
+        };
+
+        previous_line_is_error = stderr_line == "ERROR";
+
+        if previous_line_is_error {
+            continue;
+        }
+
+        match error_line {
+            Some(error_line) if line.is_none() => line = Some(error_line),
+            _ => messages.push(stderr_line)
+        }
+    }
+
+    let message = match messages.is_empty() {
+        true => String::from("Syntax error"),
+        false => messages.join("; ")
+    };
+
+    (line, message)
+}
+
 pub fn dark_mode_to_plantuml_mode(dark_mode: bool) -> &'static str {
     match dark_mode {
         true => "-darkmode",
         false => ""
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_in_light_mode_by_default() {
+        let mode = dark_mode_to_plantuml_mode(cli_dark_mode(false, false, false));
+        let args = plantuml_args(["-tpng", mode, "-failfast2"], &PlantUmlOptions::default(), ["-pipe"]);
+
+        assert_eq!(args, ["-tpng", "-failfast2", "-pipe"]);
+    }
+
+    #[test]
+    fn renders_in_dark_mode_when_asked() {
+        assert!(cli_dark_mode(false, true, false));
+        assert!(cli_dark_mode(false, false, true));
+        assert!(!cli_dark_mode(true, false, true));
+    }
+}
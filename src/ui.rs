@@ -1,6 +1,13 @@
-use crate::app::{App, APP_NAME};
-use ratatui::prelude::{Constraint, Layout, Line, Rect, Span, Stylize};
-use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use crate::app::{App, Dialog, Pane, PendingAction, APP_NAME};
+use crate::args::ARGS;
+use crate::files::color_scheme::ColorScheme;
+use crate::files::session::RenderStatus;
+use crate::files::swap::DiffLineKind;
+use crate::logic::lint::Severity;
+use crate::logic::zoom::zoomed_image;
+use ratatui::layout::Flex;
+use ratatui::prelude::{Constraint, Direction, Layout, Line, Position, Rect, Span, Style, Stylize};
+use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
 use ratatui::Frame;
 use ratatui_image::StatefulImage;
 use std::fs;
@@ -14,56 +21,484 @@ impl App<'_> {
         ])
             .areas(frame.area());
 
-        let file_name = self.input_file_path.file_name().unwrap().to_str().unwrap();
-        let title = Line::from(vec![
-            Span::raw(APP_NAME).italic().gray(),
-            Span::raw(" {").dark_gray(),
-            Span::raw(file_name),
-            Span::raw("}").dark_gray(),
-        ])
-            .centered();
+        let color_scheme = *self.color_scheme();
+        let title = Line::from(self.tab_spans()).centered();
 
-        let main_block = Block::bordered();
+        let main_block = Block::bordered()
+            .border_style(Style::new().fg(color_scheme.border))
+            .title(self.include_trail_line())
+            .title_bottom(self.lint_line())
+            .title_bottom(self.save_error_line());
         let inner_main_area = main_block.inner(main_area);
 
         frame.render_widget(title, title_area);
         frame.render_widget(main_block, main_area);
 
+        let inner_main_area = match &self.sidebar {
+            Some(_) => {
+                let sidebar_width = self.buffer.project_config.config.ui.sidebar_width.min(inner_main_area.width / 2);
+                let [sidebar_area, inner_main_area] = Layout::horizontal([
+                    Constraint::Length(sidebar_width),
+                    Constraint::Fill(1),
+                ])
+                    .areas(inner_main_area);
+
+                self.sidebar(frame, sidebar_area);
+                inner_main_area
+            },
+            None => inner_main_area
+        };
+
         self.main_area(frame, inner_main_area);
+        self.dialog(frame, main_area);
+    }
+
+    /// Name of the app followed by the open buffers, the current one between brackets
+    fn tab_spans(&self) -> Vec<Span<'static>> {
+        let color_scheme = self.color_scheme();
+        let mut tab_spans = vec![Span::raw(APP_NAME).italic().fg(color_scheme.app_name)];
+
+        for (buffer_index, buffer) in self.buffers().enumerate() {
+            let is_current = buffer_index == self.buffer_index;
+
+            match is_current {
+                true => {
+                    tab_spans.push(Span::raw(" {").fg(color_scheme.title_brackets));
+                    tab_spans.push(Span::raw(buffer.file_name()).fg(color_scheme.file_name));
+                },
+                false => {
+                    tab_spans.push(Span::raw("  "));
+                    tab_spans.push(Span::raw(buffer.file_name()).fg(color_scheme.status));
+                }
+            }
+
+            if buffer.is_dirty() {
+                tab_spans.push(Span::raw(" ●").fg(color_scheme.warning));
+            }
+
+            if is_current {
+                tab_spans.push(Span::raw("}").fg(color_scheme.title_brackets));
+            }
+        }
+
+        tab_spans
+    }
+
+    pub fn color_scheme(&self) -> &ColorScheme {
+        self.color_schemes.get(self.dark_mode)
+    }
+
+    fn lint_line(&self) -> Line<'static> {
+        let color_scheme = self.color_scheme();
+        let cursor_line = self.buffer.text_input.cursor_position.0 as usize + 1;
+
+        let cursor_diagnostic = self.buffer.lint_diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.line == cursor_line);
+
+        if let Some(diagnostic) = cursor_diagnostic {
+            let span = Span::raw(format!(" {}: {} ", diagnostic.rule, diagnostic.message));
+
+            return match diagnostic.severity {
+                Severity::Error => Line::from(span.fg(color_scheme.error)),
+                _ => Line::from(span.fg(color_scheme.warning))
+            };
+        }
+
+        let error_count = self.buffer.lint_diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).count();
+        let warning_count = self.buffer.lint_diagnostics.len() - error_count;
+
+        let mut spans = vec![];
+
+        if error_count > 0 {
+            spans.push(Span::raw(format!(" {} error(s) ", error_count)).fg(color_scheme.error));
+        }
+
+        if warning_count > 0 {
+            spans.push(Span::raw(format!(" {} warning(s) ", warning_count)).fg(color_scheme.warning));
+        }
+
+        Line::from(spans)
+    }
+
+    /// Breadcrumb of the files followed through `!include`s, shown once one was followed
+    fn include_trail_line(&self) -> Line<'static> {
+        let include_trail = self.include_trail();
+
+        if include_trail.len() < 2 {
+            return Line::default();
+        }
+
+        let color_scheme = self.color_scheme();
+        let last_index = include_trail.len() - 1;
+        let mut spans = vec![Span::raw(" ")];
+
+        for (index, file_name) in include_trail.into_iter().enumerate() {
+            match index == last_index {
+                true => spans.push(Span::raw(file_name).fg(color_scheme.file_name)),
+                false => {
+                    spans.push(Span::raw(file_name).fg(color_scheme.status));
+                    spans.push(Span::raw(" › ").fg(color_scheme.title_brackets));
+                }
+            }
+        }
+
+        spans.push(Span::raw(" "));
+        Line::from(spans)
+    }
+
+    fn save_error_line(&self) -> Line<'static> {
+        match &self.buffer.save_error {
+            Some(save_error) => Line::from(format!(" {} ", save_error))
+                .right_aligned()
+                .fg(self.color_scheme().error),
+            None => Line::default()
+        }
+    }
+
+    /// Theme of the preview, either picked or configured
+    fn theme_line(&self) -> Line<'static> {
+        let picked_theme = self.buffer.file_session.theme.as_ref();
+        let configured_theme = ARGS.theme.as_ref().or(self.buffer.project_config.config.plantuml.theme.as_ref());
+        let color_scheme = self.color_scheme();
+
+        match (picked_theme, configured_theme) {
+            (Some(theme), _) => Line::from(format!(" theme: {} ", theme)).fg(color_scheme.highlight),
+            (None, Some(theme)) => Line::from(format!(" theme: {} ", theme)).fg(color_scheme.status),
+            (None, None) => Line::default()
+        }
+    }
+
+    /// Filter and diagram files of the sidebar, each with the outcome of its last render
+    fn sidebar(&self, frame: &mut Frame, area: Rect) {
+        let Some(sidebar) = &self.sidebar else {
+            return;
+        };
+
+        let color_scheme = self.color_scheme();
+        let dir_name = sidebar.dir_path.file_name().unwrap_or(sidebar.dir_path.as_os_str()).to_string_lossy();
+
+        let block = Block::new()
+            .borders(Borders::RIGHT)
+            .border_style(Style::new().fg(color_scheme.border))
+            .title(Line::from(format!(" {} ", dir_name)).fg(color_scheme.status));
+        let [filter_area, files_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+            .areas(block.inner(area));
+
+        let filter_line = Line::from(vec![
+            Span::raw("> ").fg(color_scheme.status),
+            Span::raw(sidebar.filter.clone()).fg(color_scheme.text),
+        ]);
+
+        let file_paths = sidebar.filtered_file_paths();
+        let mut lines = file_paths.iter().enumerate().map(|(index, file_path)| {
+            let buffer = self.buffers().find(|buffer| buffer.input_file_path == *file_path);
+            let render_status = match buffer {
+                Some(buffer) => buffer.render_status(),
+                None => sidebar.render_statuses.get(*file_path).copied()
+            };
+
+            let status_span = match (render_status, buffer.is_some_and(|buffer| buffer.render_output.read().pending)) {
+                (_, true) => Span::raw("… ").fg(color_scheme.status),
+                (Some(RenderStatus::Rendered), false) => Span::raw("✓ ").fg(color_scheme.highlight),
+                (Some(RenderStatus::Failed), false) => Span::raw("✗ ").fg(color_scheme.error),
+                (None, false) => Span::raw("  ")
+            };
+
+            let name_color = match *file_path == self.buffer.input_file_path {
+                true => color_scheme.highlight,
+                false => color_scheme.text
+            };
+
+            let line = Line::from(vec![status_span, Span::raw(sidebar.relative_path(file_path)).fg(name_color)]);
+
+            match sidebar.focused && index == sidebar.selected {
+                true => line.reversed(),
+                false => line
+            }
+        })
+            .collect::<Vec<_>>();
+
+        if file_paths.is_empty() {
+            lines.push(Line::raw("No diagram").fg(color_scheme.status));
+        }
+
+        // Keeps the selected file visible
+        let vertical_offset = (sidebar.selected as u16).saturating_sub(files_area.height.saturating_sub(1));
+        let files_par = Paragraph::new(lines).scroll((vertical_offset, 0));
+
+        frame.render_widget(block, area);
+        frame.render_widget(Paragraph::new(filter_line), filter_area);
+        frame.render_widget(files_par, files_area);
+
+        if sidebar.focused {
+            let cursor_x = (sidebar.filter.chars().count() as u16 + 2).min(filter_area.width.saturating_sub(1));
+            frame.set_cursor_position(Position::new(filter_area.x + cursor_x, filter_area.y));
+        }
+    }
+
+    /// Preprocessed source below the preview, each line numbered after its origin
+    fn expanded_source(&self, frame: &mut Frame, area: Rect) {
+        let Some(expanded_source_view) = &self.expanded_source else {
+            return;
+        };
+
+        let color_scheme = self.color_scheme();
+        let expanded_source = self.buffer.expanded_source.read();
+
+        let title = match expanded_source.preprocessed {
+            true => " Expanded source ",
+            false => " Includes inlined "
+        };
+
+        let block = Block::new()
+            .borders(Borders::LEFT | Borders::TOP)
+            .border_style(Style::new().fg(color_scheme.border))
+            .title(Line::from(title).fg(color_scheme.status));
+        let inner_area = block.inner(area);
+
+        let lines = expanded_source.lines.iter().enumerate().map(|(index, expanded_line)| {
+            let origin = match expanded_line.file_path == self.buffer.input_file_path {
+                true => format!("{:>4} ", expanded_line.line + 1),
+                false => format!(
+                    "{}:{} ",
+                    expanded_line.file_path.file_name().unwrap_or_default().to_string_lossy(),
+                    expanded_line.line + 1
+                )
+            };
+
+            let line = Line::from(vec![
+                Span::raw(origin).fg(color_scheme.status),
+                Span::raw(expanded_line.text.clone()).fg(color_scheme.text),
+            ]);
+
+            match expanded_source_view.focused && index == expanded_source_view.selected {
+                true => line.reversed(),
+                false => line
+            }
+        })
+            .collect::<Vec<_>>();
+
+        // Keeps the selected line visible
+        let vertical_offset = (expanded_source_view.selected as u16).saturating_sub(inner_area.height.saturating_sub(1));
+        let expanded_source_par = Paragraph::new(lines).scroll((vertical_offset, 0));
+
+        frame.render_widget(block, area);
+        frame.render_widget(expanded_source_par, inner_area);
+    }
+
+    /// Modal centered over the editor and the preview
+    fn dialog(&self, frame: &mut Frame, area: Rect) {
+        let Some(dialog) = &self.dialog else {
+            return;
+        };
+
+        let color_scheme = self.color_scheme();
+
+        let (title, lines, selected) = match dialog {
+            Dialog::Scratchpads { scratchpads, selected } => {
+                let mut lines = scratchpads
+                    .iter()
+                    .enumerate()
+                    .map(|(index, scratchpad)| {
+                        let name_color = match scratchpad.path == self.buffer.input_file_path {
+                            true => color_scheme.highlight,
+                            false => color_scheme.text
+                        };
+
+                        let line = Line::from(vec![
+                            Span::raw(format!(" {}  ", scratchpad.modified_label())).fg(color_scheme.status),
+                            Span::raw(scratchpad.name.clone()).fg(name_color),
+                        ]);
+
+                        match index == *selected {
+                            true => line.reversed(),
+                            false => line
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                if lines.is_empty() {
+                    lines.push(Line::raw(" No scratchpad").fg(color_scheme.status));
+                }
+
+                (" Scratchpads ", lines, *selected)
+            },
+            Dialog::SaveAs { path, error } | Dialog::OpenFile { path, error } => {
+                let mut lines = vec![Line::raw(path.clone()).fg(color_scheme.text)];
+
+                if let Some(error) = error {
+                    lines.push(Line::raw(error.clone()).fg(color_scheme.error));
+                }
+
+                let title = match dialog {
+                    Dialog::OpenFile { .. } => " Open file ",
+                    _ => " Save as "
+                };
+
+                (title, lines, 0)
+            },
+            Dialog::EmbeddedDiagrams { selected } => {
+                let blocks = self.buffer.embedded.as_ref().map_or(&[][..], |document| &document.blocks);
+                let current_block_index = self.buffer.embedded.as_ref().map(|document| document.block_index);
+
+                let lines = blocks
+                    .iter()
+                    .enumerate()
+                    .map(|(index, block)| {
+                        let label_color = match Some(index) == current_block_index {
+                            true => color_scheme.highlight,
+                            false => color_scheme.text
+                        };
+
+                        let line = Line::from(vec![
+                            Span::raw(format!(" line {:<5} ", block.start_line + 1)).fg(color_scheme.status),
+                            Span::raw(block.label()).fg(label_color),
+                        ]);
+
+                        match index == *selected {
+                            true => line.reversed(),
+                            false => line
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                (" Diagrams ", lines, *selected)
+            },
+            Dialog::UnsavedChanges { pending_action } => {
+                let lines = match pending_action {
+                    PendingAction::Quit => self
+                        .buffers()
+                        .filter(|buffer| buffer.is_dirty())
+                        .map(|buffer| Line::raw(format!("\"{}\" has unsaved changes", buffer.file_name())).fg(color_scheme.warning))
+                        .collect(),
+                    PendingAction::Close | PendingAction::SwitchEmbeddedDiagram(_) => vec![Line::raw(format!("\"{}\" has unsaved changes", self.buffer.file_name())).fg(color_scheme.warning)]
+                };
+
+                (" Unsaved changes ", lines, 0)
+            },
+            Dialog::Recover { swap_file, diff, .. } => {
+                let file_name = self.buffer.input_file_path.file_name().unwrap().to_string_lossy();
+                let mut lines = vec![Line::raw(format!(
+                    "A crash left unsaved changes of \"{}\" from {}",
+                    file_name,
+                    swap_file.written_at_label()
+                )).fg(color_scheme.warning)];
+
+                if let Some(diff) = diff {
+                    lines.extend(diff.iter().map(|diff_line| match diff_line.kind {
+                        DiffLineKind::Added => Line::raw(format!("+ {}", diff_line.text)).fg(color_scheme.diff_added),
+                        DiffLineKind::Removed => Line::raw(format!("- {}", diff_line.text)).fg(color_scheme.diff_removed),
+                        DiffLineKind::Unchanged => Line::raw(format!("  {}", diff_line.text)).fg(color_scheme.text),
+                        DiffLineKind::Skipped => Line::raw(format!("  {}", diff_line.text)).fg(color_scheme.status)
+                    }));
+                }
+
+                (" Recover ", lines, 0)
+            }
+        };
+
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let [dialog_area] = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center).areas(area);
+        let [dialog_area] = Layout::horizontal([Constraint::Percentage(60)]).flex(Flex::Center).areas(dialog_area);
+
+        let block = Block::bordered()
+            .border_style(Style::new().fg(color_scheme.highlight))
+            .title(title)
+            .title_bottom(Line::from(dialog.hint()).fg(color_scheme.status));
+        let inner_dialog_area = block.inner(dialog_area);
+
+        let vertical_offset = match dialog {
+            Dialog::Recover { scroll, .. } => *scroll,
+            // Keeps the selected line visible
+            _ => (selected as u16).saturating_sub(inner_dialog_area.height.saturating_sub(1))
+        };
+        let dialog_par = Paragraph::new(lines)
+            .block(block)
+            .scroll((vertical_offset, 0));
+
+        frame.render_widget(Clear, dialog_area);
+        frame.render_widget(dialog_par, dialog_area);
+
+        if let Dialog::SaveAs { path, .. } | Dialog::OpenFile { path, .. } = dialog {
+            let cursor_x = (path.chars().count() as u16).min(inner_dialog_area.width.saturating_sub(1));
+            frame.set_cursor_position(Position::new(inner_dialog_area.x + cursor_x, inner_dialog_area.y));
+        }
     }
 
+    /// Editor and preview, side by side or one above the other, one of them possibly maximized
     pub fn main_area(&mut self, frame: &mut Frame, area: Rect) {
-        let [text_area, render_area] = Layout::horizontal(vec![
-            Constraint::Percentage(self.left_area_percentage),
-            Constraint::Percentage(100-self.left_area_percentage),
+        let editor_percentage = match self.maximized_pane {
+            Some(Pane::Editor) => 100,
+            Some(Pane::Preview) => 0,
+            None => self.editor_percentage
+        };
+
+        let (direction, divider_borders) = match (self.vertical_layout, self.maximized_pane) {
+            (_, Some(_)) => (Direction::Horizontal, Borders::NONE),
+            (true, None) => (Direction::Vertical, Borders::TOP),
+            (false, None) => (Direction::Horizontal, Borders::LEFT)
+        };
+
+        let [text_area, render_area] = Layout::new(direction, [
+            Constraint::Percentage(editor_percentage),
+            Constraint::Percentage(100 - editor_percentage),
         ])
             .areas(area);
+
+        self.split_area = area;
+
+        let render_area = match &self.expanded_source {
+            Some(_) => {
+                let [render_area, expanded_source_area] = Layout::vertical([
+                    Constraint::Percentage(50),
+                    Constraint::Percentage(50),
+                ])
+                    .areas(render_area);
+
+                self.expanded_source(frame, expanded_source_area);
+                render_area
+            },
+            None => render_area
+        };
         
-        let mut text_input_par = (self.text_input.render_fn)(&self.text_input.text);
-        let (vertical_offset, horizontal_offset) = self.text_input.calculate_scroll_offset(text_area.height, text_area.width);
-        let cursor_position = self.text_input.get_cursor_screen_position(text_area, vertical_offset, horizontal_offset);
+        let color_scheme = *self.color_scheme();
+        let mut text_input_par = (self.buffer.text_input.render_fn)(&self.buffer.text_input.text, &color_scheme);
+        let (vertical_offset, horizontal_offset) = self.buffer.text_input.update_scroll_offset(text_area.height, text_area.width);
+        let cursor_position = self.buffer.text_input.get_cursor_screen_position(text_area, vertical_offset, horizontal_offset);
         
         text_input_par = text_input_par.scroll((
             vertical_offset,
             horizontal_offset,
         ));
         
-        if let Some(cursor_position) = cursor_position {
+        let is_sidebar_focused = self.sidebar.as_ref().is_some_and(|sidebar| sidebar.focused);
+        let is_expanded_source_focused = self.expanded_source.as_ref().is_some_and(|expanded_source| expanded_source.focused);
+
+        if let Some(cursor_position) = cursor_position
+            && !is_sidebar_focused
+            && !is_expanded_source_focused
+            && !text_area.is_empty() {
             frame.set_cursor_position(cursor_position);
         }
         
         frame.render_widget(text_input_par, text_area);
 
         {
-            let output_clone = self.render_output.clone();
+            let output_clone = self.buffer.render_output.clone();
             let mut output = output_clone.write();
 
             let render_area_block = Block::new()
-                .borders(Borders::LEFT)
+                .borders(divider_borders)
+                .border_style(Style::new().fg(color_scheme.border))
+                .title_bottom(self.theme_line())
                 .title_bottom(
                     Line::from(format!("{} ms", output.time))
                         .right_aligned()
-                        .dim()
+                        .fg(color_scheme.status)
                 );
 
             let inner_render_area = render_area_block.inner(render_area);
@@ -76,7 +511,7 @@ impl App<'_> {
                     .throbber_set(BRAILLE_DOUBLE)
                     .use_type(WhichUse::Spin)
                     .label("Rendering")
-                    .to_line(&mut self.render_throbber_state)
+                    .to_line(&self.render_throbber_state)
                     .centered();
 
                 let throbber_par = Paragraph::new(vec![
@@ -90,11 +525,21 @@ impl App<'_> {
                 match &output.file_path {
                     Some(path) => match &self.picker {
                         None => {
-                            let content = fs::read_to_string(&path).unwrap();
+                            let content = fs::read_to_string(path).unwrap();
                             let render_par = Paragraph::new(content);
                             frame.render_widget(render_par, inner_render_area);
                         },
-                        Some(_) => {
+                        Some(picker) => {
+                            let zoomed_image = match &output.source_image {
+                                Some(source_image) if output.image.is_none() || output.zoom != self.zoom => Some(zoomed_image(source_image, self.zoom)),
+                                _ => None
+                            };
+
+                            if let Some(zoomed_image) = zoomed_image {
+                                output.image = Some(picker.new_resize_protocol(zoomed_image));
+                                output.zoom = self.zoom;
+                            }
+
                             if let Some(image) = output.image.as_mut() {
                                 frame.render_stateful_widget(StatefulImage::default(), inner_render_area, image);
                             }
@@ -105,7 +550,7 @@ impl App<'_> {
                         Some(render_error) => {
                             let render_error_par = Paragraph::new(render_error.clone())
                                 .wrap(Wrap { trim: false })
-                                .red();
+                                .fg(color_scheme.error);
 
                             frame.render_widget(render_error_par, inner_render_area);
                         }
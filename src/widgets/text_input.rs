@@ -1,12 +1,18 @@
+use crate::files::color_scheme::ColorScheme;
 use ratatui::layout::Rect;
 use ratatui::prelude::Position;
 use ratatui::widgets::Paragraph;
 
+/// Turns the text into a styled paragraph
+pub type RenderFn<'a> = Box<dyn Fn(&str, &ColorScheme) -> Paragraph<'a> + 'a>;
+
 pub struct TextInput<'a> {
     pub text: String,
     // y/height, x/width
     pub cursor_position: (u16, u16),
-    pub render_fn: Box<dyn Fn(&str) -> Paragraph<'a> + 'a>
+    /// First line and column shown, kept while the cursor stays visible
+    pub scroll_offset: (u16, u16),
+    pub render_fn: RenderFn<'a>
 }
 
 impl TextInput<'_> {
@@ -50,7 +56,6 @@ impl TextInput<'_> {
         self.move_cursor_right();
     }
 
-    #[allow(unused)]
     pub fn enter_str(&mut self, string: &str) {
         for char in string.chars() {
             self.enter_char(char)
@@ -145,7 +150,24 @@ impl TextInput<'_> {
         x.clamp(0, max_line_size as u16)
     }
 
-    pub fn calculate_scroll_offset(&self, viewport_height: u16, viewport_width: u16) -> (u16, u16) {
+    /// Keeps the scroll while the cursor is visible, centering the cursor once it leaves the viewport
+    pub fn update_scroll_offset(&mut self, viewport_height: u16, viewport_width: u16) -> (u16, u16) {
+        let (cursor_y, cursor_x) = self.cursor_position;
+        let (vertical_offset, horizontal_offset) = self.scroll_offset;
+        let max_vertical_offset = (self.text.lines().count() as u16).saturating_sub(viewport_height);
+
+        let is_cursor_visible = vertical_offset <= max_vertical_offset
+            && (vertical_offset..vertical_offset.saturating_add(viewport_height)).contains(&cursor_y)
+            && (horizontal_offset..horizontal_offset.saturating_add(viewport_width)).contains(&cursor_x);
+
+        if !is_cursor_visible {
+            self.scroll_offset = self.calculate_scroll_offset(viewport_height, viewport_width);
+        }
+
+        self.scroll_offset
+    }
+
+    fn calculate_scroll_offset(&self, viewport_height: u16, viewport_width: u16) -> (u16, u16) {
         let cursor_y = self.cursor_position.0;
         let cursor_x = self.cursor_position.1;
         let total_lines = self.text.lines().count() as u16;
@@ -201,6 +223,18 @@ impl TextInput<'_> {
         }
     }
 
+    /// Moves the cursor, keeping it inside the text
+    pub fn set_cursor_position(&mut self, (y, x): (u16, u16)) {
+        let y = y.min(self.text.lines().count().saturating_sub(1) as u16);
+
+        let x = match self.text.lines().nth(y as usize) {
+            Some(line) => x.min(line.chars().count() as u16),
+            None => 0
+        };
+
+        self.cursor_position = (y, x);
+    }
+
     #[allow(unused)]
     pub fn reset_cursor(&mut self) {
         self.cursor_position = (0, 0);
@@ -212,4 +246,31 @@ impl TextInput<'_> {
         self.text.clear();
         self.reset_cursor();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_input(line_count: usize) -> TextInput<'static> {
+        TextInput {
+            text: vec!["line"; line_count].join("\n"),
+            cursor_position: (0, 0),
+            scroll_offset: (0, 0),
+            render_fn: Box::new(|text, _| Paragraph::new(text.to_string())),
+        }
+    }
+
+    #[test]
+    fn keeps_a_restored_scroll_while_the_cursor_is_visible() {
+        let mut text_input = text_input(100);
+        text_input.cursor_position = (45, 0);
+        text_input.scroll_offset = (40, 0);
+
+        assert_eq!(text_input.update_scroll_offset(20, 80), (40, 0));
+
+        text_input.cursor_position = (70, 0);
+
+        assert_eq!(text_input.update_scroll_offset(20, 80), (60, 0));
+    }
+}